@@ -0,0 +1,93 @@
+use serde::Deserialize;
+use std::fs;
+
+const RES_CAMPAIGN: &str = "./res/campaign.ron";
+
+#[derive(Deserialize)]
+pub struct CampaignMap {
+    pub map: String,
+    //Seconds
+    pub par_time: u32,
+}
+
+#[derive(Deserialize)]
+pub struct Episode {
+    //Localisation key of the episode name
+    pub name: String,
+    pub maps: Vec<CampaignMap>,
+}
+
+///Ordered list of the maps that make up the game
+#[derive(Deserialize)]
+pub struct Campaign {
+    episodes: Vec<Episode>,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct CampaignPosition {
+    pub episode: usize,
+    pub map: usize,
+}
+
+impl Campaign {
+    pub fn load() -> Result<Campaign, Box<dyn std::error::Error>> {
+        let file_content = fs::read_to_string(RES_CAMPAIGN)?;
+        let campaign: Campaign = ron::from_str(&file_content)?;
+
+        Ok(campaign)
+    }
+
+    ///Maps that aren't part of any episode are played on their own
+    pub fn empty() -> Campaign {
+        Campaign {
+            episodes: Vec::new(),
+        }
+    }
+
+    pub fn find(&self, map: &str) -> Option<CampaignPosition> {
+        self.episodes
+            .iter()
+            .enumerate()
+            .find_map(|(episode, entry)| {
+                let index = entry.maps.iter().position(|m| m.map == map)?;
+                Some(CampaignPosition {
+                    episode,
+                    map: index,
+                })
+            })
+    }
+
+    pub fn episodes(&self) -> &[Episode] {
+        &self.episodes
+    }
+
+    pub fn episode(&self, position: CampaignPosition) -> &Episode {
+        &self.episodes[position.episode]
+    }
+
+    pub fn map(&self, position: CampaignPosition) -> &CampaignMap {
+        &self.episodes[position.episode].maps[position.map]
+    }
+
+    ///The map following `position`, none once the episode is finished
+    pub fn next(&self, position: CampaignPosition) -> Option<CampaignPosition> {
+        let next = CampaignPosition {
+            episode: position.episode,
+            map: position.map + 1,
+        };
+
+        if next.map < self.episodes[position.episode].maps.len() {
+            Some(next)
+        } else {
+            None
+        }
+    }
+
+    ///Orders maps as they appear in the campaign, maps outside of it come last
+    pub fn sort_maps(&self, maps: &mut [String]) {
+        maps.sort_by_key(|map| {
+            self.find(map)
+                .map_or((usize::MAX, usize::MAX), |p| (p.episode, p.map))
+        });
+    }
+}
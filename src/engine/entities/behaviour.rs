@@ -0,0 +1,105 @@
+use super::enemy::Enemy;
+use super::entity::Entity;
+use super::projectile::{Actor, Impact, Launch, Projectile};
+use super::spawntable::EntityDefinition;
+use crate::engine::navigation::{self, Cell, Pathfinder};
+use crate::engine::settings::Difficulty;
+use crate::engine::{Player, Vector2, World};
+
+const WANDER_SPEED: f32 = 1.0;
+//Seconds each frame of an effect is shown
+const EFFECT_FRAME_DURATION: f32 = 0.1;
+
+///What behaviours get to see and change besides their own entity
+pub struct Context<'a> {
+    pub world: &'a World,
+    pub player: &'a mut Player,
+    //Synced with `world`, actors with a collision radius mark their cells as occupied
+    pub pathfinder: &'a mut Pathfinder,
+    pub difficulty: Difficulty,
+    //Positions of noises made during this tick, they alert the entities in connected cells
+    pub noises: Vec<Vector2<f32>>,
+    //Living actors including the player, as they were before the update
+    pub actors: Vec<Actor>,
+    //Projectiles and effects spawned once every entity was updated
+    pub launches: Vec<Launch>,
+    //Projectile hits, their damage is dealt once every entity was updated
+    pub impacts: Vec<Impact>,
+}
+
+///Per tick logic of an entity
+pub trait Behaviour {
+    fn update(&mut self, entity: &mut Entity, context: &mut Context, delta_time: f32);
+
+    ///Called when a noise at `source` reaches the entity
+    fn alert(&mut self, _entity: &mut Entity, _source: Vector2<f32>) {}
+}
+
+///Behaviours by the names used in the spawn table
+pub fn create(name: &str, definition: &EntityDefinition) -> Option<Box<dyn Behaviour>> {
+    match name {
+        "wander" => Some(Box::new(Wander { cell: None })),
+        "guard" => Some(Box::new(Enemy::new(false, definition))),
+        "patrol" => Some(Box::new(Enemy::new(true, definition))),
+        "projectile" => definition
+            .projectile()
+            .map(|projectile| Box::new(Projectile::new(projectile)) as Box<dyn Behaviour>),
+        "effect" => Some(Box::new(Effect {
+            time: 0.0,
+            frames: definition.animations().death.clone(),
+        })),
+        _ => None,
+    }
+}
+
+///Walks straight ahead, turns around in front of walls and follows path markers
+struct Wander {
+    //Cell the entity was in during the last update
+    cell: Option<Cell>,
+}
+
+impl Behaviour for Wander {
+    fn update(&mut self, entity: &mut Entity, context: &mut Context, delta_time: f32) {
+        let cell = navigation::cell_of(entity.position);
+        if self.cell != Some(cell) {
+            self.cell = Some(cell);
+            if let Some(heading) = context.world.path_marker(cell) {
+                entity.direction = heading;
+            }
+        }
+
+        let next = entity.position + entity.direction * WANDER_SPEED * delta_time;
+        let front = next + entity.direction * entity.components.collision_radius;
+
+        let (x, y) = (front.x.floor() as i32, front.y.floor() as i32);
+        if !context.world.contains(x, y) || context.world.is_solid(x as u32, y as u32) {
+            entity.direction = -entity.direction;
+            return;
+        }
+
+        entity.position = next;
+        if let Some(sprite) = &entity.sprite {
+            sprite.borrow_mut().tick_animation();
+        }
+    }
+}
+
+///Plays the death frames once and disappears, e.g. explosions
+struct Effect {
+    time: f32,
+    frames: Vec<i32>,
+}
+
+impl Behaviour for Effect {
+    fn update(&mut self, entity: &mut Entity, _context: &mut Context, delta_time: f32) {
+        self.time += delta_time;
+
+        match self
+            .frames
+            .get((self.time / EFFECT_FRAME_DURATION) as usize)
+        {
+            Some(frame) => entity.show_frame(*frame),
+            None => entity.remove(),
+        }
+    }
+}
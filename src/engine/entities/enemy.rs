@@ -0,0 +1,322 @@
+use super::behaviour::{Behaviour, Context};
+use super::entity::Entity;
+use super::projectile::Launch;
+use super::spawntable::{Animations, EntityDefinition};
+use crate::engine::navigation::{self, Cell};
+use crate::engine::settings::Difficulty;
+use crate::engine::Vector2;
+
+//Distances in cells, durations in seconds
+const SIGHT_RANGE: f32 = 12.0;
+const ATTACK_RANGE: f32 = 6.0;
+const ATTACK_DURATION: f32 = 0.4;
+const ATTACK_COOLDOWN: f32 = 1.2;
+const ATTACK_DAMAGE: f32 = 10.0;
+const PAIN_DURATION: f32 = 0.3;
+const PATROL_SPEED: f32 = 1.0;
+const CHASE_SPEED: f32 = 2.0;
+const FRAME_DURATION: f32 = 0.2;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum State {
+    Stand,
+    Patrol,
+    Chase,
+    Attack,
+    Pain,
+    Dead,
+}
+
+///Guards standing still or patrolling until they notice the player, then chasing and shooting them
+pub struct Enemy {
+    state: State,
+    //Seconds spent in the current state
+    state_time: f32,
+    //Seconds spent walking, keeps the walk cycle going between states
+    walk_time: f32,
+    //Counts down once the player was seen, the enemy reacts when it runs out
+    reaction: Option<f32>,
+    cooldown: f32,
+    //Center of the cell currently walked to
+    waypoint: Option<Vector2<f32>>,
+    //Cell whose path marker was obeyed last
+    marker_cell: Option<Cell>,
+    animations: Animations,
+    //Projectile entity launched by attacks, hitscan shots without one
+    fires: Option<String>,
+}
+
+impl Enemy {
+    pub fn new(patrolling: bool, definition: &EntityDefinition) -> Enemy {
+        Enemy {
+            state: if patrolling {
+                State::Patrol
+            } else {
+                State::Stand
+            },
+            state_time: 0.0,
+            walk_time: 0.0,
+            reaction: None,
+            cooldown: 0.0,
+            waypoint: None,
+            marker_cell: None,
+            animations: definition.animations().clone(),
+            fires: definition.fires().map(str::to_owned),
+        }
+    }
+
+    fn reaction_time(difficulty: Difficulty) -> f32 {
+        match difficulty {
+            Difficulty::Easy => 1.0,
+            Difficulty::Normal => 0.5,
+            Difficulty::Hard => 0.2,
+        }
+    }
+
+    fn damage_factor(difficulty: Difficulty) -> f32 {
+        match difficulty {
+            Difficulty::Easy => 0.5,
+            Difficulty::Normal => 1.0,
+            Difficulty::Hard => 1.5,
+        }
+    }
+
+    fn enter(&mut self, state: State) {
+        self.state = state;
+        self.state_time = 0.0;
+    }
+
+    ///Unaware enemies only see what is in front of them
+    fn sees_player(&self, entity: &Entity, context: &Context) -> bool {
+        let to_player = *context.player.position() - entity.position;
+        if to_player.mag() > SIGHT_RANGE {
+            return false;
+        }
+
+        let aware = matches!(self.state, State::Chase | State::Attack | State::Pain);
+        let in_front = entity.direction.x * to_player.x + entity.direction.y * to_player.y > 0.0;
+
+        (aware || in_front)
+            && navigation::line_of_sight(context.world, entity.position, *context.player.position())
+    }
+
+    fn watch(&mut self, entity: &Entity, context: &mut Context, delta_time: f32) {
+        if !self.sees_player(entity, context) {
+            self.reaction = None;
+            return;
+        }
+
+        let remaining = self
+            .reaction
+            .unwrap_or_else(|| Self::reaction_time(context.difficulty))
+            - delta_time;
+
+        if remaining > 0.0 {
+            self.reaction = Some(remaining);
+            return;
+        }
+
+        //Shouting alerts everyone nearby
+        self.reaction = None;
+        self.enter(State::Chase);
+        context.noises.push(entity.position);
+    }
+
+    ///Moves towards the center of the next cell, returns false once there is nowhere to go
+    fn walk(&mut self, entity: &mut Entity, speed: f32, delta_time: f32) -> bool {
+        let waypoint = match self.waypoint {
+            Some(waypoint) => waypoint,
+            None => return false,
+        };
+
+        let to_waypoint = waypoint - entity.position;
+        let distance = to_waypoint.mag();
+        let step = speed * delta_time;
+
+        if distance <= step {
+            entity.position = waypoint;
+            self.waypoint = None;
+        } else {
+            entity.direction = to_waypoint.normalized();
+            entity.position += entity.direction * step;
+        }
+
+        self.walk_time += delta_time;
+        true
+    }
+
+    ///Walks straight along the grid, follows path markers and turns right in front of walls
+    fn patrol(&mut self, entity: &mut Entity, context: &Context, delta_time: f32) {
+        if self.waypoint.is_none() {
+            let cell = navigation::cell_of(entity.position);
+
+            //Markers turn the patrol once when it enters their cell
+            if self.marker_cell != Some(cell) {
+                self.marker_cell = Some(cell);
+                if let Some(heading) = context.world.path_marker(cell) {
+                    entity.direction = heading;
+                }
+            }
+
+            let step = navigation::grid_step(entity.direction);
+            let next = (cell.0 as i32 + step.0, cell.1 as i32 + step.1);
+            let free = context
+                .pathfinder
+                .grid()
+                .neighbours(cell)
+                .any(|(neighbour, _)| neighbour == (next.0 as u32, next.1 as u32));
+
+            if free {
+                self.waypoint = Some(navigation::cell_center((next.0 as u32, next.1 as u32)));
+            } else {
+                entity.direction = Vector2::new(-step.1 as f32, step.0 as f32).normalized();
+                return;
+            }
+        }
+
+        self.walk(entity, PATROL_SPEED, delta_time);
+    }
+
+    fn chase(&mut self, entity: &mut Entity, context: &mut Context, delta_time: f32) {
+        let player = *context.player.position();
+        let to_player = player - entity.position;
+
+        if self.cooldown <= 0.0
+            && to_player.mag() <= ATTACK_RANGE
+            && self.sees_player(entity, context)
+        {
+            entity.direction = to_player.normalized();
+            self.waypoint = None;
+            self.enter(State::Attack);
+            return;
+        }
+
+        if self.waypoint.is_none() {
+            let next = context.pathfinder.next_waypoint(entity.position, player);
+
+            //Stops next to the player instead of walking into them
+            match next {
+                Some(next) if navigation::cell_of(next) != navigation::cell_of(player) => {
+                    self.waypoint = Some(next)
+                }
+                _ => {
+                    entity.direction = to_player.normalized();
+                    return;
+                }
+            }
+        }
+
+        self.walk(entity, CHASE_SPEED, delta_time);
+    }
+
+    fn fire(&mut self, entity: &Entity, context: &mut Context) {
+        self.cooldown = ATTACK_COOLDOWN;
+        context.noises.push(entity.position);
+
+        if !self.sees_player(entity, context) {
+            return;
+        }
+
+        if let Some(projectile) = &self.fires {
+            context.launches.push(Launch {
+                name: projectile.clone(),
+                position: entity.position,
+                direction: (*context.player.position() - entity.position).normalized(),
+                faction: entity.components.faction,
+            });
+            return;
+        }
+
+        //Shots lose accuracy over distance
+        let distance = (*context.player.position() - entity.position).mag();
+        let falloff = 1.0 - 0.5 * (distance / ATTACK_RANGE).min(1.0);
+        let damage = ATTACK_DAMAGE * Self::damage_factor(context.difficulty) * falloff;
+
+        context.player.damage(damage.round() as u32);
+    }
+
+    fn animate(&self, entity: &mut Entity) {
+        let animations = &self.animations;
+
+        let (frames, index) = match self.state {
+            State::Stand => (&animations.walk, None),
+            State::Patrol | State::Chase => (
+                &animations.walk,
+                Some((self.walk_time / FRAME_DURATION) as usize % animations.walk.len().max(1)),
+            ),
+            State::Attack => (
+                &animations.attack,
+                Some((self.state_time / ATTACK_DURATION * animations.attack.len() as f32) as usize),
+            ),
+            State::Pain => (&animations.pain, Some(0)),
+            State::Dead => (
+                &animations.death,
+                Some((self.state_time / FRAME_DURATION) as usize),
+            ),
+        };
+
+        //Standing shows the idle frame, the others hold their last frame
+        let frame = match index {
+            Some(index) => frames
+                .get(index.min(frames.len().saturating_sub(1)))
+                .copied()
+                .unwrap_or(0),
+            None => 0,
+        };
+
+        entity.show_frame(frame);
+    }
+}
+
+impl Behaviour for Enemy {
+    fn update(&mut self, entity: &mut Entity, context: &mut Context, delta_time: f32) {
+        self.state_time += delta_time;
+        self.cooldown = (self.cooldown - delta_time).max(0.0);
+
+        if self.state != State::Dead {
+            //Also catches enemies that were already dead in a save game
+            if !entity.is_alive() {
+                self.enter(State::Dead);
+                entity.components.collision_radius = 0.0;
+
+                if self.animations.death.is_empty() {
+                    entity.remove();
+                    return;
+                }
+            } else if entity.take_hurt() {
+                self.waypoint = None;
+                self.enter(State::Pain);
+            }
+        }
+
+        match self.state {
+            State::Stand => self.watch(entity, context, delta_time),
+            State::Patrol => {
+                self.patrol(entity, context, delta_time);
+                self.watch(entity, context, delta_time);
+            }
+            State::Chase => self.chase(entity, context, delta_time),
+            State::Attack => {
+                if self.state_time >= ATTACK_DURATION {
+                    self.fire(entity, context);
+                    self.enter(State::Chase);
+                }
+            }
+            State::Pain => {
+                if self.state_time >= PAIN_DURATION {
+                    self.enter(State::Chase);
+                }
+            }
+            State::Dead => {}
+        }
+
+        self.animate(entity);
+    }
+
+    fn alert(&mut self, entity: &mut Entity, _source: Vector2<f32>) {
+        if entity.is_alive() && matches!(self.state, State::Stand | State::Patrol) {
+            self.reaction = None;
+            self.enter(State::Chase);
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::engine::inventory::Pickup;
+use crate::engine::sprites::{Sprite, SpriteState};
+use crate::engine::Vector2;
+
+pub type EntityId = u32;
+pub type SpriteHandle = Rc<RefCell<Sprite>>;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+pub enum Faction {
+    #[default]
+    Neutral,
+    Player,
+    Enemy,
+}
+
+///Gameplay data of an entity, all of it is optional
+#[derive(Clone)]
+pub struct Components {
+    //Entities without health can't be damaged
+    pub health: Option<u32>,
+    pub faction: Faction,
+    //Radius of the circle blocking movement, 0.0 doesn't block
+    pub collision_radius: f32,
+    pub pickup: Option<Pickup>,
+}
+
+///Game object placed in a world, it may or may not be visible through a sprite
+pub struct Entity {
+    id: EntityId,
+    //Name in the spawn table
+    name: String,
+    pub position: Vector2<f32>,
+    pub direction: Vector2<f32>,
+    pub components: Components,
+    pub sprite: Option<SpriteHandle>,
+    removed: bool,
+    //Set by damage until the behaviour reacted to it
+    hurt: bool,
+}
+
+///What is kept of an entity in save games
+#[derive(Serialize, Deserialize)]
+pub struct EntityState {
+    pub name: String,
+    pub position: Vector2<f32>,
+    pub direction: Vector2<f32>,
+    pub health: Option<u32>,
+    #[serde(default)]
+    pub faction: Option<Faction>,
+    pub sprite: Option<SpriteState>,
+}
+
+impl Entity {
+    pub fn new(
+        id: EntityId,
+        name: &str,
+        position: Vector2<f32>,
+        direction: Vector2<f32>,
+        components: Components,
+        sprite: Option<SpriteHandle>,
+    ) -> Entity {
+        Entity {
+            id,
+            name: name.to_owned(),
+            position,
+            direction,
+            components,
+            sprite,
+            removed: false,
+            hurt: false,
+        }
+    }
+
+    pub fn id(&self) -> EntityId {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    ///The entity is dropped along with its sprite after the current update
+    pub fn remove(&mut self) {
+        self.removed = true;
+    }
+
+    pub fn is_removed(&self) -> bool {
+        self.removed
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.components.health != Some(0)
+    }
+
+    ///Returns whether this killed the entity, entities without health ignore damage
+    pub fn damage(&mut self, amount: u32) -> bool {
+        match &mut self.components.health {
+            Some(health) if *health > 0 => {
+                *health = health.saturating_sub(amount);
+                self.hurt = true;
+                *health == 0
+            }
+            _ => false,
+        }
+    }
+
+    ///Whether the entity was damaged since the last call
+    pub fn take_hurt(&mut self) -> bool {
+        std::mem::replace(&mut self.hurt, false)
+    }
+
+    ///Shows a single frame of the sprites animation
+    pub fn show_frame(&self, frame: i32) {
+        if let Some(sprite) = &self.sprite {
+            sprite.borrow_mut().set_frame(frame);
+        }
+    }
+
+    ///Copies position and direction to the sprite, has to happen after every change
+    pub fn sync_sprite(&self) {
+        if let Some(sprite) = &self.sprite {
+            let mut sprite = sprite.borrow_mut();
+            sprite.set_position(self.position);
+            sprite.set_direction(self.direction);
+        }
+    }
+}
@@ -0,0 +1,389 @@
+use super::behaviour::{self, Behaviour, Context};
+use super::entity::{Entity, EntityId, EntityState, Faction};
+use super::projectile::{Actor, Impact, Target};
+use super::spawntable::SpawnTable;
+use crate::engine::navigation::{self, Pathfinder};
+use crate::engine::settings::Difficulty;
+use crate::engine::sprites::{SpriteOverrides, SpriteState};
+use crate::engine::{Player, Spawn, Vector2, World};
+
+//Number of cells a noise travels through open space
+const NOISE_RANGE: u32 = 20;
+//The player has no collision circle of their own, projectiles use this one
+const PLAYER_RADIUS: f32 = 0.3;
+
+struct Slot {
+    entity: Entity,
+    behaviour: Option<Box<dyn Behaviour>>,
+}
+
+///Owns the entities of the current world and updates them every tick
+pub struct EntityRegistry {
+    table: SpawnTable,
+    slots: Vec<Slot>,
+    next_id: EntityId,
+    pathfinder: Pathfinder,
+}
+
+impl EntityRegistry {
+    pub fn load() -> Result<EntityRegistry, Box<dyn std::error::Error>> {
+        Ok(EntityRegistry {
+            table: SpawnTable::load()?,
+            slots: Vec::new(),
+            next_id: 0,
+            pathfinder: Pathfinder::new(),
+        })
+    }
+
+    ///Replaces all entities with the ones placed by the map
+    pub fn populate(&mut self, world: &mut World) {
+        self.clear(world);
+
+        for Spawn(name, position, angle, overrides) in world.spawns().to_vec() {
+            self.spawn(
+                &name,
+                position,
+                Vector2::from_angle(angle),
+                &overrides,
+                world,
+            );
+        }
+    }
+
+    pub fn spawn(
+        &mut self,
+        name: &str,
+        position: Vector2<f32>,
+        direction: Vector2<f32>,
+        overrides: &SpriteOverrides,
+        world: &mut World,
+    ) -> EntityId {
+        let definition = self.table.get(name);
+
+        let template = definition.sprite(name);
+        let sprite = world.sprites().instantiate(template, &position, 0.0);
+        if let Some(sprite) = &sprite {
+            sprite.borrow_mut().apply(overrides);
+        } else {
+            eprintln!(
+                "Entity '{}' uses sprite '{}' which the map doesn't have",
+                name, template
+            );
+        }
+
+        let behaviour = definition.behaviour().and_then(|behaviour| {
+            let created = behaviour::create(behaviour, definition);
+            if created.is_none() {
+                eprintln!("Entity '{}' has unknown behaviour '{}'", name, behaviour);
+            }
+            created
+        });
+
+        let mut components = definition.components();
+        if let Some(sprite) = &sprite {
+            //Blocking sprites without a circle of their own block as far as they are wide
+            let sprite = sprite.borrow();
+            if sprite.template().is_blocking() && components.collision_radius == 0.0 {
+                components.collision_radius = sprite.width() / 2.0;
+            }
+        }
+
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let entity = Entity::new(id, name, position, direction, components, sprite);
+        entity.sync_sprite();
+
+        self.slots.push(Slot { entity, behaviour });
+
+        id
+    }
+
+    ///Spawns a projectile or effect on behalf of a faction, the projectile doesn't hit that faction
+    pub fn launch(
+        &mut self,
+        name: &str,
+        position: Vector2<f32>,
+        direction: Vector2<f32>,
+        faction: Faction,
+        world: &mut World,
+    ) -> EntityId {
+        let id = self.spawn(
+            name,
+            position,
+            direction,
+            &SpriteOverrides::default(),
+            world,
+        );
+        if let Some(entity) = self.get_mut(id) {
+            entity.components.faction = faction;
+        }
+        id
+    }
+
+    ///Drops the entity and its sprite right away, e.g. collected pickups
+    pub fn remove(&mut self, id: EntityId, world: &mut World) {
+        if let Some(index) = self.slots.iter().position(|slot| slot.entity.id() == id) {
+            let slot = self.slots.remove(index);
+            if let Some(sprite) = &slot.entity.sprite {
+                world.sprites().remove(sprite);
+            }
+        }
+    }
+
+    pub fn clear(&mut self, world: &mut World) {
+        for slot in self.slots.drain(..) {
+            if let Some(sprite) = &slot.entity.sprite {
+                world.sprites().remove(sprite);
+            }
+        }
+    }
+
+    ///Returns the number of enemies killed by projectiles
+    pub fn update(
+        &mut self,
+        world: &mut World,
+        player: &mut Player,
+        difficulty: Difficulty,
+        delta_time: f32,
+    ) -> u32 {
+        self.pathfinder.sync(world);
+        self.pathfinder.set_occupied(
+            self.iter()
+                .filter(|entity| entity.components.collision_radius > 0.0)
+                .map(|entity| navigation::cell_of(entity.position))
+                .collect::<Vec<_>>(),
+        );
+
+        world.set_blockers(
+            self.iter()
+                .filter(|entity| entity.components.collision_radius > 0.0)
+                .filter(|entity| {
+                    entity
+                        .sprite
+                        .as_ref()
+                        .is_some_and(|sprite| sprite.borrow().template().is_blocking())
+                })
+                .map(|entity| (entity.position, entity.components.collision_radius))
+                .collect(),
+        );
+
+        let actors = self
+            .iter()
+            .filter(|entity| entity.components.health.is_some() && entity.is_alive())
+            .filter(|entity| entity.components.collision_radius > 0.0)
+            .map(|entity| Actor {
+                target: Target::Entity(entity.id()),
+                position: entity.position,
+                radius: entity.components.collision_radius,
+                faction: entity.components.faction,
+            })
+            .chain(std::iter::once(Actor {
+                target: Target::Player,
+                position: *player.position(),
+                radius: PLAYER_RADIUS,
+                faction: Faction::Player,
+            }))
+            .collect();
+
+        let mut context = Context {
+            world,
+            player,
+            pathfinder: &mut self.pathfinder,
+            difficulty,
+            noises: Vec::new(),
+            actors,
+            launches: Vec::new(),
+            impacts: Vec::new(),
+        };
+
+        for slot in &mut self.slots {
+            if let Some(behaviour) = &mut slot.behaviour {
+                behaviour.update(&mut slot.entity, &mut context, delta_time);
+                slot.entity.sync_sprite();
+            }
+        }
+
+        let (noises, launches, impacts) = (context.noises, context.launches, context.impacts);
+
+        for noise in noises {
+            self.alert(world, noise);
+        }
+
+        let kills = impacts
+            .iter()
+            .map(|impact| self.impact(impact, world, player))
+            .sum();
+
+        for launch in launches {
+            self.launch(
+                &launch.name,
+                launch.position,
+                launch.direction,
+                launch.faction,
+                world,
+            );
+        }
+
+        //Removed entities take their sprites with them
+        let sprites = world.sprites();
+        self.slots.retain(|slot| {
+            if !slot.entity.is_removed() {
+                return true;
+            }
+
+            if let Some(sprite) = &slot.entity.sprite {
+                sprites.remove(sprite);
+            }
+            false
+        });
+
+        kills
+    }
+
+    ///Deals the damage of a projectile hit, returns the number of enemies it killed.
+    ///Splash damage reaches everyone in sight of the impact, the launching faction included
+    fn impact(&mut self, impact: &Impact, world: &World, player: &mut Player) -> u32 {
+        let mut hits: Vec<(Target, u32)> = impact
+            .target
+            .map(|target| (target, impact.damage))
+            .into_iter()
+            .collect();
+
+        if impact.splash_radius > 0.0 {
+            let splashed: Vec<(Target, Vector2<f32>)> = self
+                .iter()
+                .filter(|entity| entity.components.health.is_some() && entity.is_alive())
+                .map(|entity| (Target::Entity(entity.id()), entity.position))
+                .chain(std::iter::once((Target::Player, *player.position())))
+                .collect();
+
+            for (target, position) in splashed {
+                let distance = (position - impact.position).mag();
+                if Some(target) == impact.target
+                    || distance > impact.splash_radius
+                    || !navigation::line_of_sight(world, impact.position, position)
+                {
+                    continue;
+                }
+
+                let falloff = 1.0 - distance / impact.splash_radius;
+                hits.push((
+                    target,
+                    (impact.splash_damage as f32 * falloff).round() as u32,
+                ));
+            }
+        }
+
+        let mut kills = 0;
+        for (target, amount) in hits {
+            match target {
+                Target::Player => player.damage(amount),
+                Target::Entity(id) => {
+                    if self.damage(id, amount) == Some(Faction::Enemy) {
+                        kills += 1;
+                    }
+                }
+            }
+        }
+        kills
+    }
+
+    ///Alerts every entity in a cell connected to the source, walls and closed doors stop the sound
+    pub fn alert(&mut self, world: &World, source: Vector2<f32>) {
+        self.pathfinder.sync(world);
+        let reached = self
+            .pathfinder
+            .reachable(navigation::cell_of(source), NOISE_RANGE);
+        let grid = self.pathfinder.grid();
+
+        for slot in &mut self.slots {
+            let cell = navigation::cell_of(slot.entity.position);
+            let heard = grid.contains(cell.0 as i32, cell.1 as i32) && reached[grid.index(cell)];
+
+            if let (true, Some(behaviour)) = (heard, &mut slot.behaviour) {
+                behaviour.alert(&mut slot.entity, source);
+            }
+        }
+    }
+
+    ///Returns the faction of the entity if this killed it.
+    ///Killed entities without a behaviour to play their death are removed right away
+    pub fn damage(&mut self, id: EntityId, amount: u32) -> Option<Faction> {
+        let slot = self.slots.iter_mut().find(|slot| slot.entity.id() == id)?;
+
+        if !slot.entity.damage(amount) {
+            return None;
+        }
+
+        if slot.behaviour.is_none() {
+            slot.entity.remove();
+        }
+        Some(slot.entity.components.faction)
+    }
+
+    pub fn get_mut(&mut self, id: EntityId) -> Option<&mut Entity> {
+        self.iter_mut().find(|entity| entity.id() == id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Entity> {
+        self.slots.iter().map(|slot| &slot.entity)
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Entity> {
+        self.slots.iter_mut().map(|slot| &mut slot.entity)
+    }
+
+    pub fn states(&self, world: &World) -> Vec<EntityState> {
+        self.iter()
+            .map(|entity| EntityState {
+                name: entity.name().to_owned(),
+                position: entity.position,
+                direction: entity.direction,
+                health: entity.components.health,
+                faction: Some(entity.components.faction),
+                sprite: entity.sprite.as_ref().and_then(|sprite| {
+                    let sprite = sprite.borrow();
+                    let template = world.sprite_registry().template_name(&sprite)?;
+                    Some(sprite.state(template))
+                }),
+            })
+            .collect()
+    }
+
+    ///Replaces all entities, e.g. the ones placed by the map with those of a save game
+    pub fn restore(&mut self, states: &[EntityState], world: &mut World) {
+        self.clear(world);
+
+        for state in states {
+            let overrides = state
+                .sprite
+                .as_ref()
+                .map(SpriteState::overrides)
+                .unwrap_or_default();
+            let id = self.spawn(
+                &state.name,
+                state.position,
+                state.direction,
+                &overrides,
+                world,
+            );
+
+            if let Some(entity) = self.get_mut(id) {
+                //Entities without health stay invulnerable whatever the save says
+                if let (Some(health), Some(saved)) = (&mut entity.components.health, state.health) {
+                    *health = saved;
+                }
+                //Projectiles keep the faction that launched them
+                if let Some(faction) = state.faction {
+                    entity.components.faction = faction;
+                }
+
+                if let (Some(sprite), Some(sprite_state)) = (&entity.sprite, &state.sprite) {
+                    sprite.borrow_mut().restore(sprite_state);
+                }
+                entity.sync_sprite();
+            }
+        }
+    }
+}
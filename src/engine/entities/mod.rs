@@ -0,0 +1,9 @@
+mod behaviour;
+mod enemy;
+mod entity;
+mod entityregistry;
+mod projectile;
+mod spawntable;
+
+pub use entity::{EntityId, EntityState, Faction};
+pub use entityregistry::EntityRegistry;
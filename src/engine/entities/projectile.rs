@@ -0,0 +1,120 @@
+use super::behaviour::{Behaviour, Context};
+use super::entity::{Entity, EntityId, Faction};
+use super::spawntable::ProjectileDefinition;
+use crate::engine::{Vector2, World};
+
+//Longest distance a projectile moves before checking for collisions again, keeps it from passing through thin actors
+const MAX_STEP: f32 = 0.1;
+//Impact effects are moved back a little so they don't vanish in the wall that was hit
+const EFFECT_OFFSET: f32 = 0.3;
+
+///Something a projectile can hit
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Target {
+    Player,
+    Entity(EntityId),
+}
+
+///Living actor in the way of projectiles, collected before the behaviours are updated
+pub struct Actor {
+    pub target: Target,
+    pub position: Vector2<f32>,
+    pub radius: f32,
+    pub faction: Faction,
+}
+
+///Entity to spawn once all behaviours were updated.
+///Projectiles take the faction of whoever launched them and don't hit it
+pub struct Launch {
+    pub name: String,
+    pub position: Vector2<f32>,
+    pub direction: Vector2<f32>,
+    pub faction: Faction,
+}
+
+///Damage to deal where a projectile hit
+pub struct Impact {
+    pub position: Vector2<f32>,
+    //None for projectiles that hit a wall
+    pub target: Option<Target>,
+    //Dealt to the target only
+    pub damage: u32,
+    pub splash_radius: f32,
+    pub splash_damage: u32,
+}
+
+///Flies straight ahead until it hits a wall or an actor of another faction
+pub struct Projectile {
+    definition: ProjectileDefinition,
+}
+
+impl Projectile {
+    pub fn new(definition: &ProjectileDefinition) -> Projectile {
+        Projectile {
+            definition: definition.clone(),
+        }
+    }
+
+    fn blocked(world: &World, position: Vector2<f32>) -> bool {
+        let (x, y) = (position.x.floor() as i32, position.y.floor() as i32);
+        !world.contains(x, y) || world.is_solid(x as u32, y as u32)
+    }
+
+    fn hit(&self, entity: &mut Entity, context: &mut Context, target: Option<Target>) {
+        context.impacts.push(Impact {
+            position: entity.position,
+            target,
+            damage: self.definition.damage,
+            splash_radius: self.definition.splash_radius,
+            splash_damage: self.definition.splash_damage,
+        });
+
+        if let Some(effect) = &self.definition.impact {
+            context.launches.push(Launch {
+                name: effect.clone(),
+                position: entity.position - entity.direction * EFFECT_OFFSET,
+                direction: entity.direction,
+                faction: entity.components.faction,
+            });
+        }
+
+        entity.remove();
+    }
+}
+
+impl Behaviour for Projectile {
+    fn update(&mut self, entity: &mut Entity, context: &mut Context, delta_time: f32) {
+        let distance = self.definition.speed * delta_time;
+        let steps = (distance / MAX_STEP).ceil().max(1.0) as u32;
+        let step = entity.direction.normalized() * (distance / steps as f32);
+
+        for _ in 0..steps {
+            let next = entity.position + step;
+
+            if Self::blocked(context.world, next) {
+                self.hit(entity, context, None);
+                return;
+            }
+
+            entity.position = next;
+
+            let target = context
+                .actors
+                .iter()
+                .find(|actor| {
+                    actor.faction != entity.components.faction
+                        && (actor.position - next).mag() <= actor.radius + self.definition.radius
+                })
+                .map(|actor| actor.target);
+
+            if target.is_some() {
+                self.hit(entity, context, target);
+                return;
+            }
+        }
+
+        if let Some(sprite) = &entity.sprite {
+            sprite.borrow_mut().tick_animation();
+        }
+    }
+}
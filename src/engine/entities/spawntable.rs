@@ -0,0 +1,146 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+
+use super::entity::{Components, Faction};
+use crate::engine::inventory::Pickup;
+
+const RES_SPAWN_TABLE: &str = "./res/entities.ron";
+
+///Sprite animation frames of the behaviour states, empty lists show the first frame
+#[derive(Deserialize, Clone, Default)]
+pub struct Animations {
+    #[serde(default)]
+    pub walk: Vec<i32>,
+    #[serde(default)]
+    pub attack: Vec<i32>,
+    #[serde(default)]
+    pub pain: Vec<i32>,
+    //Entities without death frames disappear when killed
+    #[serde(default)]
+    pub death: Vec<i32>,
+}
+
+///Flight and impact of entities with the "projectile" behaviour
+#[derive(Deserialize, Clone)]
+pub struct ProjectileDefinition {
+    //Cells per second
+    pub speed: f32,
+    //Actors closer than their collision radius plus this are hit
+    #[serde(default)]
+    pub radius: f32,
+    //Dealt to the actor that was hit
+    pub damage: u32,
+    //Dealt to everyone in sight of the impact, falling off towards the edge of the radius
+    #[serde(default)]
+    pub splash_radius: f32,
+    #[serde(default)]
+    pub splash_damage: u32,
+    //Entity spawned where the projectile hit, e.g. an explosion
+    #[serde(default)]
+    pub impact: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct EntityDefinition {
+    //Sprite template of the map, defaults to the name of the entity
+    #[serde(default)]
+    sprite: Option<String>,
+    #[serde(default)]
+    health: Option<u32>,
+    #[serde(default)]
+    faction: Faction,
+    #[serde(default)]
+    collision_radius: f32,
+    #[serde(default)]
+    pickup: Option<Pickup>,
+    //Name of the behaviour updating the entity every tick, see `behaviour::create`
+    #[serde(default)]
+    behaviour: Option<String>,
+    #[serde(default)]
+    animations: Animations,
+    #[serde(default)]
+    projectile: Option<ProjectileDefinition>,
+    //Projectile entity launched by attacks instead of hitscan shots
+    #[serde(default)]
+    fires: Option<String>,
+}
+
+impl EntityDefinition {
+    ///Used for names missing in the spawn table, they are plain decorations
+    fn decoration() -> EntityDefinition {
+        EntityDefinition {
+            sprite: None,
+            health: None,
+            faction: Faction::Neutral,
+            collision_radius: 0.0,
+            pickup: None,
+            behaviour: None,
+            animations: Animations::default(),
+            projectile: None,
+            fires: None,
+        }
+    }
+
+    pub fn sprite<'a>(&'a self, name: &'a str) -> &'a str {
+        self.sprite.as_deref().unwrap_or(name)
+    }
+
+    pub fn behaviour(&self) -> Option<&str> {
+        self.behaviour.as_deref()
+    }
+
+    pub fn animations(&self) -> &Animations {
+        &self.animations
+    }
+
+    pub fn projectile(&self) -> Option<&ProjectileDefinition> {
+        self.projectile.as_ref()
+    }
+
+    pub fn fires(&self) -> Option<&str> {
+        self.fires.as_deref()
+    }
+
+    pub fn components(&self) -> Components {
+        Components {
+            health: self.health,
+            faction: self.faction,
+            collision_radius: self.collision_radius.max(0.0),
+            pickup: self.pickup.clone(),
+        }
+    }
+}
+
+///Entity definitions by the names used in the `sprites` list of a maps `layout.ron`
+#[derive(Deserialize)]
+pub struct SpawnTable {
+    entities: HashMap<String, EntityDefinition>,
+
+    #[serde(skip, default = "EntityDefinition::decoration")]
+    decoration: EntityDefinition,
+}
+
+impl SpawnTable {
+    pub fn load() -> Result<SpawnTable, Box<dyn std::error::Error>> {
+        let file_content = fs::read_to_string(RES_SPAWN_TABLE)?;
+        let table: SpawnTable = ron::from_str(&file_content)?;
+
+        for (name, definition) in &table.entities {
+            if definition.behaviour() == Some("projectile") && definition.projectile.is_none() {
+                return Err(format!(
+                    "Entity '{}' of {} is a projectile without a projectile definition",
+                    name, RES_SPAWN_TABLE
+                )
+                .into());
+            }
+        }
+
+        Ok(table)
+    }
+
+    ///Names without an entry get a decoration showing the sprite of the same name
+    pub fn get(&self, name: &str) -> &EntityDefinition {
+        self.entities.get(name).unwrap_or(&self.decoration)
+    }
+}
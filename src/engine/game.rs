@@ -0,0 +1,488 @@
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+use super::campaign::{Campaign, CampaignPosition};
+use super::entities::{EntityRegistry, Faction};
+use super::inventory::{Key, Pickup};
+use super::savegame::SaveGame;
+use super::ui::{Font, Hud, Intermission, Overlay, WeaponView};
+use super::weapons::{self, Arsenal, Shot};
+use super::{I18n, Player, Settings, Vector2, World};
+
+//How long notices like the name of a freshly loaded map are shown
+const NOTICE_DURATION: Duration = Duration::from_secs(3);
+
+//Single player always uses the first start of a map
+const PLAYER_SLOT: u32 = 0;
+
+//How far in front of the player exits can be used, sampled in USE_STEPS steps
+const USE_DISTANCE: f32 = 1.0;
+const USE_STEPS: u32 = 4;
+
+//Distance at which the player collects pickups
+const PICKUP_RADIUS: f32 = 0.5;
+
+//Opacity of the red flash at its strongest
+const MAX_FLASH_ALPHA: f32 = 0.5;
+
+///What the player achieved on the current map
+#[derive(Serialize, Deserialize, Clone, Copy, Default)]
+pub struct LevelStats {
+    pub kills: u32,
+    pub total_kills: u32,
+    pub secrets: u32,
+    pub total_secrets: u32,
+    pub items: u32,
+    pub total_items: u32,
+    //Seconds spent playing, paused time doesn't count
+    pub time: f32,
+}
+
+///A map that is being played
+pub struct Game {
+    world: World,
+    player: Player,
+    entities: EntityRegistry,
+    arsenal: Arsenal,
+    level_name: String,
+    notice: String,
+    notice_shown: Instant,
+
+    //None for maps that aren't part of the campaign
+    position: Option<CampaignPosition>,
+    stats: LevelStats,
+    //Secret cells the player entered already
+    found_secrets: Vec<(u32, u32)>,
+    //Hazard damage not dealt yet, the player loses whole points of health only
+    hazard_damage: f32,
+    //Shown once the level was finished
+    intermission: Option<Intermission>,
+}
+
+impl Game {
+    pub fn start(
+        map: &str,
+        campaign: &Campaign,
+        settings: &Settings,
+        i18n: &I18n,
+    ) -> Result<Game, Box<dyn std::error::Error>> {
+        //Placed once the world is loaded and its starts are known
+        let mut player = Player::from(Vector2::new(0.0, 0.0), 0.0, settings.fov());
+        let mut world = World::load(map, settings.view_resolution(), &player)?;
+        Self::spawn(&world, &mut player, settings)?;
+
+        let mut entities = EntityRegistry::load()?;
+        entities.populate(&mut world);
+
+        let mut game = Game {
+            world,
+            player,
+            entities,
+            arsenal: Arsenal::load()?,
+            level_name: String::new(),
+            notice: String::new(),
+            notice_shown: Instant::now(),
+            position: campaign.find(map),
+            stats: LevelStats::default(),
+            found_secrets: Vec::new(),
+            hazard_damage: 0.0,
+            intermission: None,
+        };
+        game.count_totals();
+        game.localise(i18n);
+        game.notify(i18n.format("now_playing", &[("map", &game.level_name)]));
+
+        Ok(game)
+    }
+
+    ///Loads the map of the save game like a fresh start and applies the saved state on top
+    pub fn load(
+        slot: u32,
+        campaign: &Campaign,
+        settings: &Settings,
+        i18n: &I18n,
+    ) -> Result<Game, Box<dyn std::error::Error>> {
+        let save = SaveGame::load(slot)?;
+
+        let mut game = Game::start(&save.map, campaign, settings, i18n)?;
+        game.world.set_tiles(&save.tiles);
+        game.player.restore(&save.player);
+        game.entities.restore(&save.entities, &mut game.world);
+        if let Some(weapons) = &save.weapons {
+            game.arsenal.restore(weapons);
+        }
+        game.stats = save.stats;
+        game.found_secrets = save.secrets;
+        game.notify(i18n.get_translation("game_loaded").to_owned());
+
+        Ok(game)
+    }
+
+    pub fn save(&self, slot: u32) -> Result<(), Box<dyn std::error::Error>> {
+        SaveGame::new(
+            self.world.identifier().clone(),
+            self.player.state(),
+            self.entities.states(&self.world),
+            self.world.tile_changes(),
+            self.stats,
+            self.found_secrets.clone(),
+            self.arsenal.state(),
+        )
+        .save(slot)
+    }
+
+    ///Loads the next map of the episode, the player keeps health, ammo, score and weapons.
+    ///Returns false if the episode is over
+    pub fn advance(
+        &mut self,
+        campaign: &Campaign,
+        settings: &Settings,
+        i18n: &I18n,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let next = match self.position.and_then(|position| campaign.next(position)) {
+            Some(next) => next,
+            None => return Ok(false),
+        };
+
+        self.enter(&campaign.map(next).map, settings, i18n)?;
+        self.position = Some(next);
+
+        Ok(true)
+    }
+
+    ///Restarts the current map after the player died, only the score is kept
+    pub fn respawn(
+        &mut self,
+        settings: &Settings,
+        i18n: &I18n,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.player.revive();
+        self.arsenal.reset();
+
+        let map = self.world.identifier().clone();
+        self.enter(&map, settings, i18n)
+    }
+
+    ///Replaces the world with a fresh copy of the map and places the player at its start
+    fn enter(
+        &mut self,
+        map: &str,
+        settings: &Settings,
+        i18n: &I18n,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut world = World::load(map, settings.view_resolution(), &self.player)?;
+        Self::spawn(&world, &mut self.player, settings)?;
+        self.entities.populate(&mut world);
+        self.world = world;
+
+        self.stats = LevelStats::default();
+        self.found_secrets.clear();
+        self.count_totals();
+        self.intermission = None;
+        self.localise(i18n);
+        self.notify(i18n.format("now_playing", &[("map", &self.level_name)]));
+
+        Ok(())
+    }
+
+    fn spawn(
+        world: &World,
+        player: &mut Player,
+        settings: &Settings,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let start = world
+            .player_start(settings.difficulty(), PLAYER_SLOT)
+            .ok_or_else(|| {
+                format!(
+                    "Map '{}' has no player start for {:?}",
+                    world.identifier(),
+                    settings.difficulty()
+                )
+            })?;
+
+        player.place(start.position, start.angle);
+
+        Ok(())
+    }
+
+    ///Enemies, pickups and secrets the map starts out with
+    fn count_totals(&mut self) {
+        self.stats.total_secrets = self.world.secret_cells().len() as u32;
+        self.stats.total_kills = self
+            .entities
+            .iter()
+            .filter(|entity| entity.components.faction == Faction::Enemy)
+            .count() as u32;
+        self.stats.total_items = self
+            .entities
+            .iter()
+            .filter(|entity| entity.components.pickup.is_some())
+            .count() as u32;
+    }
+
+    ///Has to be called when the language changes
+    pub fn localise(&mut self, i18n: &I18n) {
+        self.level_name = i18n.get_translation(self.world.identifier()).to_owned();
+    }
+
+    ///Shows a line of text in the top left corner for a few seconds
+    pub fn notify(&mut self, text: String) {
+        self.notice = text;
+        self.notice_shown = Instant::now();
+    }
+
+    pub fn resize(&mut self, view_resolution: (u32, u32)) {
+        self.world.resize(view_resolution);
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.intermission.is_some()
+    }
+
+    ///The player died and the game waits for the input to restart the map
+    pub fn awaiting_respawn(&self) -> bool {
+        !self.is_finished() && self.player.can_respawn()
+    }
+
+    ///Uses whatever the player is facing, exits end the level and keys open locked doors
+    pub fn activate(&mut self, campaign: &Campaign, i18n: &I18n) {
+        let position = *self.player.position();
+        let direction = *self.player.direction();
+
+        for step in 1..=USE_STEPS {
+            let reach = USE_DISTANCE * step as f32 / USE_STEPS as f32;
+            let x = (position.x + direction.x * reach).floor() as i32;
+            let y = (position.y + direction.y * reach).floor() as i32;
+
+            if !self.world.contains(x, y) {
+                return;
+            }
+            let (x, y) = (x as u32, y as u32);
+
+            if self.world.is_solid(x, y) {
+                if self.world.is_exit(x, y) {
+                    self.finish(campaign);
+                } else if let Some(key) = self.world.lock(x, y) {
+                    self.unlock(x, y, key, i18n);
+                }
+                return;
+            }
+        }
+    }
+
+    fn unlock(&mut self, x: u32, y: u32, key: Key, i18n: &I18n) {
+        if self.player.inventory().has_key(key) {
+            self.world.set_tiles(&[(x, y, 0)]);
+            return;
+        }
+
+        let message = match key {
+            Key::Blue => "door_locked_blue",
+            Key::Yellow => "door_locked_yellow",
+            Key::Green => "door_locked_green",
+            Key::Red => "door_locked_red",
+        };
+        self.notify(i18n.get_translation(message).to_owned());
+    }
+
+    fn finish(&mut self, campaign: &Campaign) {
+        self.player.stop_movement();
+
+        let (par_time, episode, episode_finished) = match self.position {
+            Some(position) => (
+                Some(campaign.map(position).par_time),
+                Some(campaign.episode(position).name.clone()),
+                campaign.next(position).is_none(),
+            ),
+            None => (None, None, false),
+        };
+
+        self.intermission = Some(Intermission::new(
+            self.stats,
+            par_time,
+            episode,
+            episode_finished,
+        ));
+    }
+
+    pub fn update(
+        &mut self,
+        mouse_delta: &Vector2<f32>,
+        settings: &Settings,
+        i18n: &I18n,
+        delta_time: f32,
+    ) {
+        if self.is_finished() {
+            return;
+        }
+
+        self.stats.time += delta_time;
+
+        self.player.update(&self.world, delta_time);
+        if !self.player.is_dead() {
+            self.player
+                .rotate_by_mouse(mouse_delta, settings.mouse_sensitivity(), delta_time);
+            self.hurt_by_floor(delta_time);
+        }
+        self.player.copy_to_gpu();
+
+        if self.player.is_dead() {
+            self.arsenal.set_trigger(false);
+        } else if let Some(shot) = self.arsenal.update(&mut self.player, delta_time) {
+            self.shoot(shot);
+        }
+
+        self.stats.kills += self.entities.update(
+            &mut self.world,
+            &mut self.player,
+            settings.difficulty(),
+            delta_time,
+        );
+
+        if !self.player.is_dead() {
+            self.collect_pickups();
+            self.find_secrets(i18n);
+        }
+    }
+
+    ///Entering a secret cell for the first time counts it as found
+    fn find_secrets(&mut self, i18n: &I18n) {
+        let position = *self.player.position();
+        let cell = (position.x.floor() as u32, position.y.floor() as u32);
+
+        if self.world.secret_cells().contains(&cell) && !self.found_secrets.contains(&cell) {
+            self.found_secrets.push(cell);
+            self.stats.secrets += 1;
+            self.notify(i18n.get_translation("secret_found").to_owned());
+        }
+    }
+
+    ///Hazard floors deal their damage per second, jumping players are out of reach
+    fn hurt_by_floor(&mut self, delta_time: f32) {
+        let position = *self.player.position();
+        let hazard = self.world.hazard(position.x as u32, position.y as u32);
+
+        match hazard {
+            Some(damage) if self.player.on_ground() => {
+                self.hazard_damage += damage * delta_time;
+
+                let whole = self.hazard_damage.floor();
+                if whole >= 1.0 {
+                    self.hazard_damage -= whole;
+                    self.player.damage(whole as u32);
+                }
+            }
+            _ => self.hazard_damage = 0.0,
+        }
+    }
+
+    ///Pickups the player overlaps are taken unless the player has no use for them
+    fn collect_pickups(&mut self) {
+        let position = *self.player.position();
+        let touched: Vec<_> = self
+            .entities
+            .iter()
+            .filter(|entity| (entity.position - position).mag() <= PICKUP_RADIUS)
+            .filter_map(|entity| Some((entity.id(), entity.components.pickup.clone()?)))
+            .collect();
+
+        for (id, pickup) in touched {
+            let taken = match &pickup {
+                Pickup::Weapon(name) => self.arsenal.give(name),
+                _ => self.player.inventory_mut().pick_up(&pickup),
+            };
+
+            if taken {
+                self.entities.remove(id, &mut self.world);
+                self.stats.items += 1;
+            }
+        }
+    }
+
+    pub fn set_trigger(&mut self, pulled: bool) {
+        self.arsenal.set_trigger(pulled);
+    }
+
+    ///`index` is the number key minus one
+    pub fn select_weapon(&mut self, index: usize) {
+        self.arsenal.select(index);
+    }
+
+    fn shoot(&mut self, shot: Shot) {
+        let origin = *self.player.position();
+        let direction = self.player.direction().rotated(shot.angle);
+
+        if let Some(projectile) = &shot.projectile {
+            self.entities.launch(
+                projectile,
+                origin,
+                direction,
+                Faction::Player,
+                &mut self.world,
+            );
+        } else if let Some(hit) =
+            weapons::trace(&self.world, &self.entities, origin, direction, shot.range)
+        {
+            if self.entities.damage(hit.entity, shot.damage) == Some(Faction::Enemy) {
+                self.stats.kills += 1;
+            }
+        }
+
+        if !shot.silent {
+            self.entities.alert(&self.world, origin);
+        }
+    }
+
+    pub fn render(
+        &mut self,
+        hud: &Hud,
+        weapon_view: &WeaponView,
+        overlay: &mut Overlay,
+        font: &Font,
+        i18n: &I18n,
+    ) {
+        if let Some(intermission) = &self.intermission {
+            intermission.draw(overlay, font, i18n, &self.level_name);
+            return;
+        }
+
+        self.world.render(&self.player);
+        if !self.player.is_dead() {
+            weapon_view.draw(overlay, self.arsenal.frame(), self.arsenal.lowered());
+        }
+
+        let (view_width, view_height) = self.world.resolution();
+        if self.player.flash() > 0.0 {
+            overlay.fill(
+                (0.0, 0.0),
+                (view_width as f32, view_height as f32),
+                [1.0, 0.0, 0.0, self.player.flash() * MAX_FLASH_ALPHA],
+            );
+        }
+
+        hud.draw(overlay, font, &self.player, &self.level_name);
+
+        if self.awaiting_respawn() {
+            let text = i18n.get_translation("respawn");
+            let (width, height) = font.measure(text, 1.0);
+            overlay.draw_text(
+                font,
+                text,
+                (
+                    (view_width as f32 - width as f32) / 2.0,
+                    (view_height as f32 - height as f32) / 2.0,
+                ),
+                [1.0, 1.0, 1.0, 1.0],
+                1.0,
+            );
+        }
+
+        if self.notice_shown.elapsed() < NOTICE_DURATION {
+            overlay.draw_text(font, &self.notice, (8.0, 8.0), [1.0, 1.0, 1.0, 1.0], 1.0);
+        }
+    }
+
+    pub fn player(&mut self) -> &mut Player {
+        &mut self.player
+    }
+}
@@ -0,0 +1,188 @@
+use serde::{Deserialize, Serialize};
+
+use super::weapons::AmmoType;
+
+pub const MAX_HEALTH: u32 = 100;
+pub const MAX_ARMOR: u32 = 100;
+//Part of the damage armor takes instead of health, as long as there is armor left
+const ARMOR_ABSORPTION: f32 = 0.5;
+const START_AMMO: u32 = 8;
+const MAX_BULLETS: u32 = 99;
+const MAX_ROCKETS: u32 = 25;
+
+///Opens the locked doors of the same color, keys only last for the map they were found on
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum Key {
+    Blue,
+    Yellow,
+    Green,
+    Red,
+}
+
+///What the player receives when picking an entity up
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+pub enum Pickup {
+    Health(u32),
+    Armor(u32),
+    Ammo(AmmoType, u32),
+    Key(Key),
+    //Treasure
+    Score(u32),
+    //Name in the weapon table, handled by the arsenal
+    Weapon(String),
+}
+
+///Everything the player carries besides the weapons
+pub struct Inventory {
+    health: u32,
+    armor: u32,
+    bullets: u32,
+    rockets: u32,
+    keys: Vec<Key>,
+    score: u32,
+}
+
+///What is kept of the inventory in save games
+pub struct InventoryState {
+    pub health: u32,
+    pub armor: u32,
+    pub bullets: u32,
+    pub rockets: u32,
+    pub keys: Vec<Key>,
+    pub score: u32,
+}
+
+impl Inventory {
+    pub fn new() -> Inventory {
+        Inventory {
+            health: MAX_HEALTH,
+            armor: 0,
+            bullets: START_AMMO,
+            rockets: 0,
+            keys: Vec::new(),
+            score: 0,
+        }
+    }
+
+    ///Saved values are capped like picked up ones
+    pub fn restore(&mut self, state: InventoryState) {
+        self.health = state.health.min(MAX_HEALTH);
+        self.armor = state.armor.min(MAX_ARMOR);
+        self.bullets = state.bullets.min(Self::max_ammo(AmmoType::Bullets));
+        self.rockets = state.rockets.min(Self::max_ammo(AmmoType::Rockets));
+        self.keys = state.keys;
+        self.score = state.score;
+    }
+
+    ///Starts over after a death, only the score is kept
+    pub fn revive(&mut self) {
+        *self = Inventory {
+            score: self.score,
+            ..Inventory::new()
+        };
+    }
+
+    pub fn health(&self) -> u32 {
+        self.health
+    }
+
+    pub fn armor(&self) -> u32 {
+        self.armor
+    }
+
+    ///Armor soaks up part of the damage, returns the health that was lost
+    pub fn damage(&mut self, amount: u32) -> u32 {
+        let absorbed = ((amount as f32 * ARMOR_ABSORPTION).round() as u32).min(self.armor);
+        self.armor -= absorbed;
+
+        let lost = (amount - absorbed).min(self.health);
+        self.health -= lost;
+        lost
+    }
+
+    pub fn max_ammo(ammo: AmmoType) -> u32 {
+        match ammo {
+            AmmoType::Bullets => MAX_BULLETS,
+            AmmoType::Rockets => MAX_ROCKETS,
+        }
+    }
+
+    pub fn ammo(&self, ammo: AmmoType) -> u32 {
+        match ammo {
+            AmmoType::Bullets => self.bullets,
+            AmmoType::Rockets => self.rockets,
+        }
+    }
+
+    fn ammo_mut(&mut self, ammo: AmmoType) -> &mut u32 {
+        match ammo {
+            AmmoType::Bullets => &mut self.bullets,
+            AmmoType::Rockets => &mut self.rockets,
+        }
+    }
+
+    ///Takes one round, returns false if there is none left
+    pub fn use_ammo(&mut self, ammo: AmmoType) -> bool {
+        let count = self.ammo_mut(ammo);
+
+        if *count == 0 {
+            return false;
+        }
+        *count -= 1;
+        true
+    }
+
+    pub fn keys(&self) -> &[Key] {
+        &self.keys
+    }
+
+    pub fn has_key(&self, key: Key) -> bool {
+        self.keys.contains(&key)
+    }
+
+    ///Keys don't open anything on other maps
+    pub fn drop_keys(&mut self) {
+        self.keys.clear();
+    }
+
+    pub fn score(&self) -> u32 {
+        self.score
+    }
+
+    ///Returns false if the player has no use for the pickup, it stays where it is then.
+    ///Weapons aren't part of the inventory and are never taken here
+    pub fn pick_up(&mut self, pickup: &Pickup) -> bool {
+        match pickup {
+            Pickup::Health(amount) => {
+                if self.health >= MAX_HEALTH {
+                    return false;
+                }
+                self.health = (self.health + amount).min(MAX_HEALTH);
+            }
+            Pickup::Armor(amount) => {
+                if self.armor >= MAX_ARMOR {
+                    return false;
+                }
+                self.armor = (self.armor + amount).min(MAX_ARMOR);
+            }
+            Pickup::Ammo(ammo, amount) => {
+                let max = Self::max_ammo(*ammo);
+                let count = self.ammo_mut(*ammo);
+                if *count >= max {
+                    return false;
+                }
+                *count = (*count + amount).min(max);
+            }
+            Pickup::Key(key) => {
+                if self.has_key(*key) {
+                    return false;
+                }
+                self.keys.push(*key);
+            }
+            Pickup::Score(points) => self.score += points,
+            Pickup::Weapon(_) => return false,
+        }
+
+        true
+    }
+}
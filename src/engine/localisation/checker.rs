@@ -0,0 +1,171 @@
+use std::collections::BTreeSet;
+
+use super::i18n::{placeholders, DEFAULT_LANGUAGE};
+use super::{I18n, PluralCategory};
+use crate::engine::{Campaign, World};
+
+#[derive(Default)]
+struct Report {
+    errors: u32,
+    warnings: u32,
+}
+
+impl Report {
+    fn error(&mut self, lang: &str, message: String) {
+        eprintln!("error [{}]: {}", lang, message);
+        self.errors += 1;
+    }
+
+    fn warning(&mut self, lang: &str, message: String) {
+        eprintln!("warning [{}]: {}", lang, message);
+        self.warnings += 1;
+    }
+}
+
+///Union of the placeholders of all plural forms
+fn placeholder_set<'a>(forms: &[(&'a str, &'a str)]) -> BTreeSet<&'a str> {
+    forms
+        .iter()
+        .flat_map(|(_, text)| placeholders(text))
+        .collect()
+}
+
+///Plural categories the language actually distinguishes
+fn used_categories(lang: &str) -> BTreeSet<&'static str> {
+    (0..200)
+        .map(|n| PluralCategory::of(lang, n).as_str())
+        .collect()
+}
+
+fn check_forms(report: &mut Report, lang: &str, key: &str, forms: &[(&str, &str)]) {
+    //Plain strings show up as a single "other" form
+    if forms.len() == 1 && forms[0].0 == PluralCategory::Other.as_str() {
+        return;
+    }
+
+    for (category, _) in forms {
+        if !PluralCategory::ALL.iter().any(|c| c.as_str() == *category) {
+            report.error(
+                lang,
+                format!("\"{}\" has unknown plural category \"{}\"", key, category),
+            );
+        }
+    }
+
+    for category in used_categories(lang) {
+        if !forms.iter().any(|(c, _)| *c == category) {
+            let message = format!("\"{}\" has no \"{}\" plural form", key, category);
+
+            //Without "other" there is nothing to fall back to
+            if category == PluralCategory::Other.as_str() {
+                report.error(lang, message);
+            } else {
+                report.warning(lang, message);
+            }
+        }
+    }
+}
+
+///Checks all translations against the default language and every map and episode for a display name.
+///Problems are printed to stderr, returns false if any errors were found
+pub fn check_coverage() -> bool {
+    let mut report = Report::default();
+
+    let reference = match I18n::from(DEFAULT_LANGUAGE) {
+        Ok(reference) => reference,
+        Err(e) => {
+            report.error(DEFAULT_LANGUAGE, format!("Failed to load: {}", e));
+            return false;
+        }
+    };
+
+    let mut reference_keys: Vec<&str> = reference.own_keys().collect();
+    reference_keys.sort_unstable();
+
+    for key in &reference_keys {
+        let forms = reference.own_forms(key).unwrap_or_default();
+        check_forms(&mut report, DEFAULT_LANGUAGE, key, &forms);
+    }
+
+    for map in World::available_maps() {
+        if !reference_keys.contains(&map.as_str()) {
+            report.error(
+                DEFAULT_LANGUAGE,
+                format!("Map \"{}\" has no display name", map),
+            );
+        }
+    }
+
+    match Campaign::load() {
+        Ok(campaign) => {
+            for episode in campaign.episodes() {
+                if !reference_keys.contains(&episode.name.as_str()) {
+                    report.error(
+                        DEFAULT_LANGUAGE,
+                        format!("Episode \"{}\" has no display name", episode.name),
+                    );
+                }
+            }
+        }
+        Err(e) => report.error(
+            DEFAULT_LANGUAGE,
+            format!("Failed to load the campaign: {}", e),
+        ),
+    }
+
+    let languages = I18n::available_languages();
+
+    for lang in languages.iter().filter(|lang| *lang != DEFAULT_LANGUAGE) {
+        let i18n = match I18n::from(lang) {
+            Ok(i18n) => i18n,
+            Err(e) => {
+                report.error(lang, format!("Failed to load: {}", e));
+                continue;
+            }
+        };
+
+        for key in &reference_keys {
+            let forms = match i18n.own_forms(key) {
+                Some(forms) => forms,
+                None => {
+                    report.error(lang, format!("Missing key \"{}\"", key));
+                    continue;
+                }
+            };
+
+            check_forms(&mut report, lang, key, &forms);
+
+            let expected = placeholder_set(&reference.own_forms(key).unwrap_or_default());
+            let found = placeholder_set(&forms);
+
+            if expected != found {
+                report.error(
+                    lang,
+                    format!(
+                        "Placeholders of \"{}\" don't match {}: expected {:?}, found {:?}",
+                        key, DEFAULT_LANGUAGE, expected, found
+                    ),
+                );
+            }
+        }
+
+        let mut unused: Vec<&str> = i18n
+            .own_keys()
+            .filter(|key| !reference_keys.contains(key))
+            .collect();
+        unused.sort_unstable();
+
+        for key in unused {
+            report.warning(lang, format!("Unused key \"{}\"", key));
+        }
+    }
+
+    println!(
+        "Checked {} languages: {} errors, {} warnings",
+        languages.len(),
+        report.errors,
+        report.warnings
+    );
+
+    report.errors == 0
+}
@@ -0,0 +1,228 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Write};
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use super::PluralCategory;
+
+const RES_I18N: &str = "./res/localisation/";
+pub const DEFAULT_LANGUAGE: &str = "en_GB";
+
+///A translation is either a plain string or a map from plural category to string
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Entry {
+    Text(String),
+    Plural(HashMap<String, String>),
+}
+
+impl Entry {
+    fn form(&self, category: PluralCategory) -> &str {
+        match self {
+            Entry::Text(text) => text,
+            Entry::Plural(forms) => forms
+                .get(category.as_str())
+                .or_else(|| forms.get(PluralCategory::Other.as_str()))
+                .map(String::as_str)
+                .unwrap_or(""),
+        }
+    }
+}
+
+type Dictionary = HashMap<String, Entry>;
+
+///Localisation system, keys missing in the active language fall back to the default language and then to the key itself
+pub struct I18n {
+    language: String,
+    dict: Dictionary,
+    //Empty if the active language is the default language
+    fallback: Dictionary,
+    //Keys that were already reported as missing
+    missing: RefCell<HashSet<String>>,
+}
+
+impl I18n {
+    pub fn from(lang: &str) -> Result<I18n, Box<dyn std::error::Error>> {
+        let mut i18n = I18n {
+            language: String::new(),
+            dict: HashMap::new(),
+            fallback: HashMap::new(),
+            missing: RefCell::new(HashSet::new()),
+        };
+
+        i18n.set_language(lang)?;
+
+        Ok(i18n)
+    }
+
+    fn load_dictionary(lang: &str) -> Result<Dictionary, Box<dyn std::error::Error>> {
+        let file_content = fs::read_to_string(&(RES_I18N.to_owned() + lang + ".ron"))?;
+
+        Ok(ron::from_str(&file_content)?)
+    }
+
+    ///Switches the active language, the previous one stays active if the new one fails to load
+    pub fn set_language(&mut self, lang: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let dict = I18n::load_dictionary(lang)?;
+
+        let fallback = if lang == DEFAULT_LANGUAGE {
+            HashMap::new()
+        } else {
+            match I18n::load_dictionary(DEFAULT_LANGUAGE) {
+                Ok(fallback) => fallback,
+                Err(e) => {
+                    eprintln!(
+                        "Warning: Failed to load default language {}: {}",
+                        DEFAULT_LANGUAGE, e
+                    );
+                    HashMap::new()
+                }
+            }
+        };
+
+        self.language = lang.to_owned();
+        self.dict = dict;
+        self.fallback = fallback;
+        self.missing.borrow_mut().clear();
+
+        Ok(())
+    }
+
+    pub fn is_available(lang: &str) -> bool {
+        Path::new(&(RES_I18N.to_owned() + lang + ".ron")).is_file()
+    }
+
+    ///Language codes of all translation files in the localisation folder, sorted
+    pub fn available_languages() -> Vec<String> {
+        let entries = match fs::read_dir(RES_I18N) {
+            Ok(entries) => entries,
+            Err(e) => {
+                eprintln!("Warning: Failed to list languages in {}: {}", RES_I18N, e);
+                return vec![DEFAULT_LANGUAGE.to_owned()];
+            }
+        };
+
+        let mut languages: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "ron"))
+            .filter_map(|path| Some(path.file_stem()?.to_str()?.to_owned()))
+            .collect();
+
+        languages.sort();
+
+        languages
+    }
+
+    ///Keys defined by the active language itself, without the fallback
+    pub(super) fn own_keys(&self) -> impl Iterator<Item = &str> {
+        self.dict.keys().map(String::as_str)
+    }
+
+    ///All forms of `key` in the active language as (plural category, text) pairs
+    pub(super) fn own_forms(&self, key: &str) -> Option<Vec<(&str, &str)>> {
+        match self.dict.get(key)? {
+            Entry::Text(text) => Some(vec![(PluralCategory::Other.as_str(), text.as_str())]),
+            Entry::Plural(forms) => Some(
+                forms
+                    .iter()
+                    .map(|(category, text)| (category.as_str(), text.as_str()))
+                    .collect(),
+            ),
+        }
+    }
+
+    ///Returns the entry for `key` together with the language it was found in
+    fn lookup(&self, key: &str) -> Option<(&Entry, &str)> {
+        if let Some(entry) = self.dict.get(key) {
+            return Some((entry, &self.language));
+        }
+
+        let fallback = self.fallback.get(key);
+
+        if self.missing.borrow_mut().insert(key.to_owned()) {
+            match fallback {
+                Some(_) => eprintln!(
+                    "Warning: Missing translation for \"{}\" in {}, using {}",
+                    key, self.language, DEFAULT_LANGUAGE
+                ),
+                None => eprintln!("Warning: Missing translation for \"{}\"", key),
+            }
+        }
+
+        fallback.map(|entry| (entry, DEFAULT_LANGUAGE))
+    }
+
+    pub fn get_translation<'a>(&'a self, key: &'a str) -> &'a str {
+        match self.lookup(key) {
+            Some((entry, _)) => entry.form(PluralCategory::Other),
+            None => key,
+        }
+    }
+
+    ///Translates `key` and replaces its `{name}` placeholders with the matching arguments
+    pub fn format(&self, key: &str, args: &[(&str, &dyn Display)]) -> String {
+        substitute(self.get_translation(key), args)
+    }
+
+    ///Like `format`, but picks the plural form for `count`, which is also available as `{count}`
+    pub fn plural(&self, key: &str, count: u64, args: &[(&str, &dyn Display)]) -> String {
+        let template = match self.lookup(key) {
+            Some((entry, lang)) => entry.form(PluralCategory::of(lang, count)),
+            None => key,
+        };
+
+        let mut all_args: Vec<(&str, &dyn Display)> = vec![("count", &count)];
+        all_args.extend_from_slice(args);
+
+        substitute(template, &all_args)
+    }
+}
+
+///Names of all `{name}` placeholders in `template`
+pub(super) fn placeholders(template: &str) -> Vec<&str> {
+    let mut names = Vec::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => break,
+        };
+
+        names.push(&rest[start + 1..end]);
+        rest = &rest[end + 1..];
+    }
+
+    names
+}
+
+///Replaces `{name}` with the argument called name, unknown placeholders are kept as they are
+fn substitute(template: &str, args: &[(&str, &dyn Display)]) -> String {
+    let mut result = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        result.push_str(&rest[..start]);
+
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => break,
+        };
+
+        let name = &rest[start + 1..end];
+
+        match args.iter().find(|(arg, _)| *arg == name) {
+            Some((_, value)) => write!(result, "{}", value).unwrap(),
+            None => result.push_str(&rest[start..=end]),
+        }
+
+        rest = &rest[end + 1..];
+    }
+
+    result.push_str(rest);
+    result
+}
@@ -0,0 +1,7 @@
+mod checker;
+mod i18n;
+mod plural;
+
+pub use checker::check_coverage;
+pub use i18n::I18n;
+pub use plural::PluralCategory;
@@ -0,0 +1,111 @@
+///CLDR plural categories, translation files use their lowercase names as keys
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PluralCategory {
+    Zero,
+    One,
+    Two,
+    Few,
+    Many,
+    Other,
+}
+
+impl PluralCategory {
+    pub const ALL: [PluralCategory; 6] = [
+        PluralCategory::Zero,
+        PluralCategory::One,
+        PluralCategory::Two,
+        PluralCategory::Few,
+        PluralCategory::Many,
+        PluralCategory::Other,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PluralCategory::Zero => "zero",
+            PluralCategory::One => "one",
+            PluralCategory::Two => "two",
+            PluralCategory::Few => "few",
+            PluralCategory::Many => "many",
+            PluralCategory::Other => "other",
+        }
+    }
+
+    ///Picks the plural category of `n` for the given language code (e.g. "en_GB")
+    pub fn of(lang: &str, n: u64) -> PluralCategory {
+        let language = lang.split('_').next().unwrap_or(lang);
+
+        let n10 = n % 10;
+        let n100 = n % 100;
+
+        match language {
+            //No grammatical number
+            "ja" | "ko" | "zh" | "vi" | "th" | "id" => PluralCategory::Other,
+            //Zero and one share the singular
+            "fr" => {
+                if n <= 1 {
+                    PluralCategory::One
+                } else {
+                    PluralCategory::Other
+                }
+            }
+            "ru" | "uk" | "be" => {
+                if n10 == 1 && n100 != 11 {
+                    PluralCategory::One
+                } else if (2..=4).contains(&n10) && !(12..=14).contains(&n100) {
+                    PluralCategory::Few
+                } else {
+                    PluralCategory::Many
+                }
+            }
+            "pl" => {
+                if n == 1 {
+                    PluralCategory::One
+                } else if (2..=4).contains(&n10) && !(12..=14).contains(&n100) {
+                    PluralCategory::Few
+                } else {
+                    PluralCategory::Many
+                }
+            }
+            "cs" | "sk" => match n {
+                1 => PluralCategory::One,
+                2..=4 => PluralCategory::Few,
+                _ => PluralCategory::Other,
+            },
+            //English and most other european languages
+            _ => {
+                if n == 1 {
+                    PluralCategory::One
+                } else {
+                    PluralCategory::Other
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PluralCategory;
+
+    #[test]
+    fn english_has_one_and_other() {
+        assert_eq!(PluralCategory::of("en_GB", 0), PluralCategory::Other);
+        assert_eq!(PluralCategory::of("en_GB", 1), PluralCategory::One);
+        assert_eq!(PluralCategory::of("en_GB", 2), PluralCategory::Other);
+        assert_eq!(PluralCategory::of("en_GB", 11), PluralCategory::Other);
+        assert_eq!(PluralCategory::of("en_GB", 21), PluralCategory::Other);
+    }
+
+    #[test]
+    fn german_has_one_and_other() {
+        assert_eq!(PluralCategory::of("de_DE", 0), PluralCategory::Other);
+        assert_eq!(PluralCategory::of("de_DE", 1), PluralCategory::One);
+        assert_eq!(PluralCategory::of("de_DE", 5), PluralCategory::Other);
+        assert_eq!(PluralCategory::of("de_DE", 101), PluralCategory::Other);
+    }
+
+    #[test]
+    fn language_without_region() {
+        assert_eq!(PluralCategory::of("de", 1), PluralCategory::One);
+    }
+}
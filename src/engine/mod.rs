@@ -1,13 +1,27 @@
+mod campaign;
+mod entities;
+mod game;
+mod inventory;
 mod localisation;
+mod navigation;
 pub mod player;
+mod savegame;
 mod settings;
 mod sprites;
+mod ui;
 mod util;
+mod weapons;
 mod world;
 
-pub use localisation::I18n;
+pub use campaign::Campaign;
+pub use game::Game;
+pub use localisation::{check_coverage, I18n};
 pub use player::Player;
-pub use settings::Settings;
+pub use savegame::{SaveGame, QUICKSAVE_SLOT};
+pub use settings::{Settings, WindowMode};
 pub use sprites::Sprite;
+pub use ui::{
+    Font, Gamepad, Hud, Menu, MenuAction, MenuInput, Overlay, Screen, Setting, WeaponView,
+};
 pub use util::Vector2;
-pub use world::World;
+pub use world::{Spawn, World};
@@ -0,0 +1,58 @@
+use std::collections::BinaryHeap;
+
+use super::navgrid::{Frontier, NavGrid};
+use super::Cell;
+
+///Shortest path between two cells, without `from` but including `to`.
+///Cells marked in `occupied` are avoided unless they are the target.
+///None if `to` can't be reached
+pub fn find_path(grid: &NavGrid, from: Cell, to: Cell, occupied: &[bool]) -> Option<Vec<Cell>> {
+    if grid.is_blocked(from) || grid.is_blocked(to) {
+        return None;
+    }
+
+    let (width, height) = grid.size();
+    let mut costs = vec![f32::INFINITY; (width * height) as usize];
+    let mut came_from: Vec<Option<Cell>> = vec![None; costs.len()];
+
+    costs[grid.index(from)] = 0.0;
+    let mut open = BinaryHeap::new();
+    open.push(Frontier {
+        priority: NavGrid::estimate(from, to),
+        cell: from,
+    });
+
+    while let Some(Frontier { cell, .. }) = open.pop() {
+        if cell == to {
+            let mut path = vec![to];
+            while let Some(previous) = came_from[grid.index(*path.last()?)] {
+                path.push(previous);
+            }
+
+            //The start is where the walk already is
+            path.pop();
+            path.reverse();
+            return Some(path);
+        }
+
+        let cost = costs[grid.index(cell)];
+
+        for (neighbour, step) in grid.neighbours(cell) {
+            let index = grid.index(neighbour);
+            if neighbour != to && occupied.get(index).copied().unwrap_or(false) {
+                continue;
+            }
+
+            if cost + step < costs[index] {
+                costs[index] = cost + step;
+                came_from[index] = Some(cell);
+                open.push(Frontier {
+                    priority: cost + step + NavGrid::estimate(neighbour, to),
+                    cell: neighbour,
+                });
+            }
+        }
+    }
+
+    None
+}
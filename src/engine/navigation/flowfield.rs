@@ -0,0 +1,64 @@
+use std::collections::BinaryHeap;
+
+use super::navgrid::{Frontier, NavGrid};
+use super::Cell;
+
+///Next cell towards a target for every cell of the grid.
+///Costs one search no matter how many actors follow it
+pub struct FlowField {
+    width: u32,
+    next: Vec<Option<Cell>>,
+}
+
+impl FlowField {
+    ///Searches outwards from the target, moves cost the same in both directions
+    pub fn new(grid: &NavGrid, target: Cell) -> FlowField {
+        let (width, height) = grid.size();
+        let mut costs = vec![f32::INFINITY; (width * height) as usize];
+        let mut next = vec![None; costs.len()];
+
+        if !grid.is_blocked(target) {
+            costs[grid.index(target)] = 0.0;
+
+            let mut open = BinaryHeap::new();
+            open.push(Frontier {
+                priority: 0.0,
+                cell: target,
+            });
+
+            while let Some(Frontier { priority, cell }) = open.pop() {
+                //Stale entry, the cell was reached cheaper since it was queued
+                if priority > costs[grid.index(cell)] {
+                    continue;
+                }
+
+                for (neighbour, step) in grid.neighbours(cell) {
+                    let index = grid.index(neighbour);
+
+                    if priority + step < costs[index] {
+                        costs[index] = priority + step;
+                        next[index] = Some(cell);
+                        open.push(Frontier {
+                            priority: priority + step,
+                            cell: neighbour,
+                        });
+                    }
+                }
+            }
+        }
+
+        FlowField { width, next }
+    }
+
+    fn index(&self, cell: Cell) -> Option<usize> {
+        if cell.0 >= self.width {
+            return None;
+        }
+        Some((cell.0 + cell.1 * self.width) as usize).filter(|index| *index < self.next.len())
+    }
+
+    ///Cell to move to from `cell`, None at the target or if it can't be reached
+    pub fn next(&self, cell: Cell) -> Option<Cell> {
+        self.next[self.index(cell)?]
+    }
+}
@@ -0,0 +1,28 @@
+mod astar;
+mod flowfield;
+mod navgrid;
+mod pathfinder;
+mod raycast;
+
+pub use navgrid::NavGrid;
+pub use pathfinder::Pathfinder;
+pub use raycast::{cast_ray, line_of_sight};
+
+use super::Vector2;
+
+pub type Cell = (u32, u32);
+
+///Grid cell containing the position
+pub fn cell_of(position: Vector2<f32>) -> Cell {
+    (position.x.max(0.0) as u32, position.y.max(0.0) as u32)
+}
+
+pub fn cell_center(cell: Cell) -> Vector2<f32> {
+    Vector2::new(cell.0 as f32 + 0.5, cell.1 as f32 + 0.5)
+}
+
+///Neighbouring cell the direction points at as offset, one of the eight around the cell
+pub fn grid_step(direction: Vector2<f32>) -> (i32, i32) {
+    let direction = direction.normalized();
+    (direction.x.round() as i32, direction.y.round() as i32)
+}
@@ -0,0 +1,152 @@
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+
+use super::Cell;
+
+const STRAIGHT_COST: f32 = 1.0;
+const DIAGONAL_COST: f32 = std::f32::consts::SQRT_2;
+
+const NEIGHBOURS: [(i32, i32); 8] = [
+    (1, 0),
+    (-1, 0),
+    (0, 1),
+    (0, -1),
+    (1, 1),
+    (1, -1),
+    (-1, 1),
+    (-1, -1),
+];
+
+///Which cells of a map block movement
+pub struct NavGrid {
+    width: u32,
+    height: u32,
+    blocked: Vec<bool>,
+}
+
+impl NavGrid {
+    pub fn new(width: u32, height: u32, is_blocked: impl Fn(u32, u32) -> bool) -> NavGrid {
+        let blocked = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| is_blocked(x, y))
+            .collect();
+
+        NavGrid {
+            width,
+            height,
+            blocked,
+        }
+    }
+
+    pub fn empty() -> NavGrid {
+        NavGrid::new(0, 0, |_, _| true)
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        x >= 0 && y >= 0 && (x as u32) < self.width && (y as u32) < self.height
+    }
+
+    ///Position of the cell in per cell lists, x + y * width
+    pub fn index(&self, cell: Cell) -> usize {
+        (cell.0 + cell.1 * self.width) as usize
+    }
+
+    ///Cells outside of the grid are blocked
+    pub fn is_blocked(&self, cell: Cell) -> bool {
+        cell.0 >= self.width || self.blocked.get(self.index(cell)).copied().unwrap_or(true)
+    }
+
+    fn is_free(&self, x: i32, y: i32) -> bool {
+        self.contains(x, y) && !self.is_blocked((x as u32, y as u32))
+    }
+
+    ///Free cells one move away with the cost of the move.
+    ///Diagonal moves can't cut corners, both cells next to them have to be free as well
+    pub fn neighbours(&self, cell: Cell) -> impl Iterator<Item = (Cell, f32)> + '_ {
+        let (x, y) = (cell.0 as i32, cell.1 as i32);
+
+        NEIGHBOURS.iter().filter_map(move |(dx, dy)| {
+            if !self.is_free(x + dx, y + dy) {
+                return None;
+            }
+
+            let diagonal = *dx != 0 && *dy != 0;
+            if diagonal && !(self.is_free(x + dx, y) && self.is_free(x, y + dy)) {
+                return None;
+            }
+
+            let cost = if diagonal {
+                DIAGONAL_COST
+            } else {
+                STRAIGHT_COST
+            };
+            Some((((x + dx) as u32, (y + dy) as u32), cost))
+        })
+    }
+
+    ///Lower bound of the cost between two cells, exact on an empty grid
+    pub fn estimate(from: Cell, to: Cell) -> f32 {
+        let dx = (from.0 as f32 - to.0 as f32).abs();
+        let dy = (from.1 as f32 - to.1 as f32).abs();
+
+        STRAIGHT_COST * dx.max(dy) + (DIAGONAL_COST - STRAIGHT_COST) * dx.min(dy)
+    }
+
+    ///Cells connected to `start` by at most `max_steps` moves, indexed like `index`
+    pub fn reachable(&self, start: Cell, max_steps: u32) -> Vec<bool> {
+        let mut steps = vec![u32::MAX; self.blocked.len()];
+        if start.0 >= self.width || start.1 >= self.height {
+            return vec![false; steps.len()];
+        }
+
+        steps[self.index(start)] = 0;
+        let mut queue = VecDeque::from(vec![start]);
+
+        while let Some(cell) = queue.pop_front() {
+            let step = steps[self.index(cell)];
+            if step >= max_steps {
+                continue;
+            }
+
+            for (neighbour, _) in self.neighbours(cell) {
+                let index = self.index(neighbour);
+                if steps[index] == u32::MAX {
+                    steps[index] = step + 1;
+                    queue.push_back(neighbour);
+                }
+            }
+        }
+
+        steps.into_iter().map(|step| step != u32::MAX).collect()
+    }
+}
+
+///Cell with the cost to get there, ordered so `BinaryHeap` pops the cheapest first
+pub struct Frontier {
+    pub priority: f32,
+    pub cell: Cell,
+}
+
+impl PartialEq for Frontier {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Frontier {}
+
+impl PartialOrd for Frontier {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Frontier {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.priority.total_cmp(&self.priority)
+    }
+}
@@ -0,0 +1,241 @@
+use std::collections::HashMap;
+
+use super::astar;
+use super::flowfield::FlowField;
+use super::navgrid::NavGrid;
+use super::{cell_center, cell_of, Cell};
+use crate::engine::{Vector2, World};
+
+//Flow fields are kept per target cell, a chased player leaves many of them behind
+const MAX_FLOW_FIELDS: usize = 16;
+
+///Path queries on the grid of the current map.
+///The grid and the flow fields are cached until the map or its layout changes
+pub struct Pathfinder {
+    //Map identifier and layout revision the grid was built from
+    source: Option<(String, u32)>,
+    grid: NavGrid,
+    //Cells blocked by actors, see `set_occupied`
+    occupied: Vec<bool>,
+    flow_fields: HashMap<Cell, FlowField>,
+}
+
+impl Pathfinder {
+    pub fn new() -> Pathfinder {
+        Pathfinder {
+            source: None,
+            grid: NavGrid::empty(),
+            occupied: Vec::new(),
+            flow_fields: HashMap::new(),
+        }
+    }
+
+    ///Rebuilds the grid if the world differs from the one of the last call
+    pub fn sync(&mut self, world: &World) {
+        let source = (world.identifier().clone(), world.revision());
+        if self.source.as_ref() != Some(&source) {
+            self.set_grid(world.nav_grid());
+            self.source = Some(source);
+        }
+    }
+
+    fn set_grid(&mut self, grid: NavGrid) {
+        let (width, height) = grid.size();
+        self.occupied = vec![false; (width * height) as usize];
+        self.flow_fields.clear();
+        self.grid = grid;
+    }
+
+    ///Marks the cells taken by actors, replacing the previous ones
+    pub fn set_occupied(&mut self, cells: impl IntoIterator<Item = Cell>) {
+        self.occupied
+            .iter_mut()
+            .for_each(|occupied| *occupied = false);
+
+        for cell in cells {
+            if !self.grid.is_blocked(cell) {
+                let index = self.grid.index(cell);
+                self.occupied[index] = true;
+            }
+        }
+    }
+
+    pub fn is_occupied(&self, cell: Cell) -> bool {
+        !self.grid.is_blocked(cell) && self.occupied[self.grid.index(cell)]
+    }
+
+    ///Waypoints from one position to another as cell centers, the last one is `to` itself.
+    ///Goes around occupied cells, None if there is no way
+    pub fn find_path(&self, from: Vector2<f32>, to: Vector2<f32>) -> Option<Vec<Vector2<f32>>> {
+        let cells = astar::find_path(&self.grid, cell_of(from), cell_of(to), &self.occupied)?;
+
+        let mut path: Vec<Vector2<f32>> = cells.into_iter().map(cell_center).collect();
+        path.pop();
+        path.push(to);
+
+        Some(path)
+    }
+
+    ///Flow field towards a cell, shared by everyone heading there. Ignores occupied cells
+    pub fn flow_field(&mut self, target: Cell) -> &FlowField {
+        if !self.flow_fields.contains_key(&target) && self.flow_fields.len() >= MAX_FLOW_FIELDS {
+            self.flow_fields.clear();
+        }
+
+        let grid = &self.grid;
+        self.flow_fields
+            .entry(target)
+            .or_insert_with(|| FlowField::new(grid, target))
+    }
+
+    ///Center of the next cell on the way from one position to another, None once there
+    pub fn next_waypoint(&mut self, from: Vector2<f32>, to: Vector2<f32>) -> Option<Vector2<f32>> {
+        let next = self.flow_field(cell_of(to)).next(cell_of(from))?;
+
+        //Someone is in the way, look for a way around them
+        if self.is_occupied(next) && next != cell_of(to) {
+            return self.find_path(from, to)?.first().copied();
+        }
+
+        Some(cell_center(next))
+    }
+
+    ///Cells connected to `start` by at most `max_steps` moves, indexed by x + y * width
+    pub fn reachable(&self, start: Cell, max_steps: u32) -> Vec<bool> {
+        self.grid.reachable(start, max_steps)
+    }
+
+    pub fn grid(&self) -> &NavGrid {
+        &self.grid
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(map: &str) -> Pathfinder {
+        let mut pathfinder = Pathfinder::new();
+        pathfinder.set_grid(World::load_nav_grid(map).unwrap());
+        pathfinder
+    }
+
+    fn cost(from: Cell, path: &[Cell]) -> f32 {
+        std::iter::once(from)
+            .chain(path.iter().copied())
+            .zip(path)
+            .map(|(a, b)| NavGrid::estimate(a, *b))
+            .sum()
+    }
+
+    #[test]
+    fn paths_go_around_walls() {
+        for map in ["test_map_1", "test_map_2"] {
+            let pathfinder = load(map);
+            let grid = pathfinder.grid();
+
+            let from = Vector2::new(1.5, 1.5);
+            let to = Vector2::new(12.25, 1.75);
+            let path = pathfinder.find_path(from, to).unwrap();
+
+            let last = path.last().unwrap();
+            assert!(last.x == to.x && last.y == to.y);
+
+            //Every step is a legal move, which also rules out cut corners
+            let mut cell = cell_of(from);
+            for waypoint in &path {
+                let next = cell_of(*waypoint);
+                assert!(grid
+                    .neighbours(cell)
+                    .any(|(neighbour, _)| neighbour == next));
+                cell = next;
+            }
+        }
+    }
+
+    #[test]
+    fn corners_are_not_cut() {
+        let pathfinder = load("test_map_2");
+        let neighbours: Vec<Cell> = pathfinder
+            .grid()
+            .neighbours((5, 1))
+            .map(|(cell, _)| cell)
+            .collect();
+
+        //(6, 1) is a wall, so (6, 2) is only reachable around it
+        assert!(neighbours.contains(&(5, 2)));
+        assert!(!neighbours.contains(&(6, 2)));
+        assert!(!neighbours.contains(&(6, 1)));
+    }
+
+    #[test]
+    fn walls_are_unreachable() {
+        let pathfinder = load("test_map_1");
+        let from = Vector2::new(2.5, 2.5);
+
+        assert!(pathfinder.find_path(from, Vector2::new(0.5, 0.5)).is_none());
+        assert!(pathfinder
+            .find_path(from, Vector2::new(13.5, 3.5))
+            .is_none());
+        assert!(pathfinder
+            .find_path(from, Vector2::new(-4.0, 40.0))
+            .is_none());
+    }
+
+    #[test]
+    fn occupied_cells_are_avoided() {
+        let mut pathfinder = load("test_map_2");
+        let blocked = [(3, 2), (3, 3), (3, 4), (5, 3)];
+        pathfinder.set_occupied(blocked.iter().copied());
+
+        let path = pathfinder
+            .find_path(Vector2::new(1.5, 3.5), Vector2::new(5.5, 3.5))
+            .unwrap();
+
+        //The target itself may be occupied, it's usually who is chased
+        assert_eq!(cell_of(*path.last().unwrap()), (5, 3));
+        for waypoint in &path[..path.len() - 1] {
+            assert!(!blocked.contains(&cell_of(*waypoint)));
+        }
+
+        pathfinder.set_occupied((1..6).map(|y| (3, y)));
+        assert!(pathfinder
+            .find_path(Vector2::new(1.5, 3.5), Vector2::new(5.5, 3.5))
+            .is_none());
+    }
+
+    #[test]
+    fn flow_fields_match_shortest_paths() {
+        let mut pathfinder = load("test_map_1");
+        let target = (12, 1);
+        let starts = [(1, 1), (1, 5), (5, 1), (8, 5), (12, 5), (12, 1)];
+
+        for start in starts {
+            let path = astar::find_path(pathfinder.grid(), start, target, &[]).unwrap();
+            let expected = cost(start, &path);
+
+            let field = pathfinder.flow_field(target);
+            let mut cell = start;
+            let mut followed = Vec::new();
+            while let Some(next) = field.next(cell) {
+                followed.push(next);
+                cell = next;
+            }
+
+            assert_eq!(cell, target);
+            assert!((cost(start, &followed) - expected).abs() < 1e-4);
+        }
+
+        assert!(pathfinder.flow_field(target).next((0, 0)).is_none());
+    }
+
+    #[test]
+    fn new_grids_drop_the_cache() {
+        let mut pathfinder = load("test_map_1");
+        pathfinder.flow_field((2, 2));
+        assert_eq!(pathfinder.flow_fields.len(), 1);
+
+        pathfinder.set_grid(World::load_nav_grid("test_map_2").unwrap());
+        assert!(pathfinder.flow_fields.is_empty());
+    }
+}
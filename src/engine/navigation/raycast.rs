@@ -0,0 +1,65 @@
+use crate::engine::{Vector2, World};
+
+///Distance along a normalized direction to the first cell hiding what lies behind it,
+///None if there is none within `max_distance`. Walks every grid cell the ray passes
+///through, like the wall raycaster does
+pub fn cast_ray(
+    world: &World,
+    from: Vector2<f32>,
+    direction: Vector2<f32>,
+    max_distance: f32,
+) -> Option<f32> {
+    let mut cell = (from.x.floor() as i32, from.y.floor() as i32);
+
+    let step = (
+        if direction.x < 0.0 { -1 } else { 1 },
+        if direction.y < 0.0 { -1 } else { 1 },
+    );
+
+    //Distance along the ray between two cell borders on each axis
+    let delta_dist = ((1.0 / direction.x).abs(), (1.0 / direction.y).abs());
+
+    //Distance along the ray to the next cell border on each axis
+    let mut side_dist = (
+        match direction.x {
+            x if x < 0.0 => (from.x - cell.0 as f32) * delta_dist.0,
+            x if x > 0.0 => (cell.0 as f32 + 1.0 - from.x) * delta_dist.0,
+            _ => f32::INFINITY,
+        },
+        match direction.y {
+            y if y < 0.0 => (from.y - cell.1 as f32) * delta_dist.1,
+            y if y > 0.0 => (cell.1 as f32 + 1.0 - from.y) * delta_dist.1,
+            _ => f32::INFINITY,
+        },
+    );
+
+    loop {
+        let distance = side_dist.0.min(side_dist.1);
+        if distance > max_distance {
+            return None;
+        }
+
+        if side_dist.0 < side_dist.1 {
+            side_dist.0 += delta_dist.0;
+            cell.0 += step.0;
+        } else {
+            side_dist.1 += delta_dist.1;
+            cell.1 += step.1;
+        }
+
+        if !world.contains(cell.0, cell.1) || world.blocks_sight(cell.0 as u32, cell.1 as u32) {
+            return Some(distance);
+        }
+    }
+}
+
+///Whether nothing blocks the view between two points
+pub fn line_of_sight(world: &World, from: Vector2<f32>, to: Vector2<f32>) -> bool {
+    let delta = to - from;
+    let distance = delta.mag();
+    if distance == 0.0 {
+        return true;
+    }
+
+    cast_ray(world, from, delta * (1.0 / distance), distance).is_none()
+}
@@ -1,11 +1,32 @@
 use crate::gpu::{self, SSBO};
+use serde::{Deserialize, Serialize};
 
+use super::inventory::{Inventory, InventoryState, Key};
 use super::util::Vector2;
+use super::weapons::AmmoType;
 use super::world::World;
 use crate::gpu::ISSBO;
 
 const ROTATION_SPEED: f32 = 0.5;
+const PITCH_SPEED: f32 = 0.05;
+const MAX_PITCH: f32 = 0.4;
 const MOVEMENT_SPEED: f32 = 5.0;
+const CROUCH_MOVEMENT_FACTOR: f32 = 0.5;
+
+//Eye heights in wall units, the floor is at 0.0 and the ceiling at 1.0
+const EYE_HEIGHT: f32 = 0.5;
+const CROUCH_EYE_HEIGHT: f32 = 0.3;
+const CROUCH_SPEED: f32 = 1.5;
+const JUMP_VELOCITY: f32 = 1.6;
+const GRAVITY: f32 = 6.0;
+
+//A dead player's camera falls down to the floor, respawning is possible once it lay there for a moment
+const DEAD_EYE_HEIGHT: f32 = 0.1;
+const DEATH_DURATION: f32 = 1.5;
+
+//Health lost for the strongest red flash, the flash fades by 1.0 in FLASH_FADE_TIME seconds
+const FLASH_DAMAGE: f32 = 40.0;
+const FLASH_FADE_TIME: f32 = 0.6;
 
 type MoveDir = usize;
 pub const FORWARDS: MoveDir = 0;
@@ -15,7 +36,15 @@ pub const LEFT: MoveDir = 3;
 
 pub struct Player {
     transform: Transform,
+    fov: f32,
     movement: [bool; 4], //Forward,Backward,Right,Left
+    crouching: bool,
+    vertical_velocity: f32,
+    inventory: Inventory,
+    //Strength of the red flash after taking damage, 0.0 to 1.0
+    flash: f32,
+    //Seconds since the player died
+    dead_time: f32,
     ssbo: SSBO,
 }
 
@@ -24,27 +53,120 @@ struct Transform {
     position: Vector2<f32>,
     direction: Vector2<f32>,
     camera_plane: Vector2<f32>,
+    //Vertical look as a fraction of the screen height, positive looks up
+    pitch: f32,
+    eye_height: f32,
 }
 
 impl ISSBO for Transform {}
 
+///What is kept of the player in save games
+#[derive(Serialize, Deserialize)]
+pub struct PlayerState {
+    position: Vector2<f32>,
+    direction: Vector2<f32>,
+    pitch: f32,
+    health: u32,
+    #[serde(default)]
+    armor: u32,
+    ammo: u32,
+    #[serde(default)]
+    rockets: u32,
+    #[serde(default)]
+    keys: Vec<Key>,
+    score: u32,
+}
+
 impl Player {
-    pub fn from(position: Vector2<f32>) -> Player {
+    ///`angle` is the facing direction and `fov` the horizontal field of view, both in degrees
+    pub fn from(position: Vector2<f32>, angle: f32, fov: f32) -> Player {
         let transform = Transform {
             position,
-            direction: Vector2::new(-0.75, 0.0),
-            camera_plane: Vector2::new(0.0, 0.66),
+            direction: Vector2::from_angle(angle),
+            camera_plane: Vector2::new(0.0, 0.0),
+            pitch: 0.0,
+            eye_height: EYE_HEIGHT,
         };
 
         let ssbo = gpu::SSBO::from(2, &transform, gl::DYNAMIC_DRAW);
 
-        Player {
+        let mut player = Player {
             transform,
+            fov,
             movement: [false, false, false, false],
+            crouching: false,
+            vertical_velocity: 0.0,
+            inventory: Inventory::new(),
+            flash: 0.0,
+            dead_time: 0.0,
             ssbo,
+        };
+        player.update_camera_plane();
+
+        player
+    }
+
+    ///Moves the player to the start of another map, the inventory is kept apart from the keys
+    pub fn place(&mut self, position: Vector2<f32>, angle: f32) {
+        self.transform.position = position;
+        self.transform.direction = Vector2::from_angle(angle);
+        self.transform.pitch = 0.0;
+        self.transform.eye_height = EYE_HEIGHT;
+        self.vertical_velocity = 0.0;
+        self.inventory.drop_keys();
+        self.stop_movement();
+        self.update_camera_plane();
+    }
+
+    pub fn state(&self) -> PlayerState {
+        PlayerState {
+            position: self.transform.position,
+            direction: self.transform.direction,
+            pitch: self.transform.pitch,
+            health: self.inventory.health(),
+            armor: self.inventory.armor(),
+            ammo: self.inventory.ammo(AmmoType::Bullets),
+            rockets: self.inventory.ammo(AmmoType::Rockets),
+            keys: self.inventory.keys().to_vec(),
+            score: self.inventory.score(),
         }
     }
 
+    pub fn restore(&mut self, state: &PlayerState) {
+        self.transform.position = state.position;
+        self.transform.direction = state.direction;
+        self.transform.pitch = state.pitch.clamp(-MAX_PITCH, MAX_PITCH);
+        self.transform.eye_height = EYE_HEIGHT;
+        self.vertical_velocity = 0.0;
+        self.inventory.restore(InventoryState {
+            health: state.health,
+            armor: state.armor,
+            bullets: state.ammo,
+            rockets: state.rockets,
+            keys: state.keys.clone(),
+            score: state.score,
+        });
+        self.flash = 0.0;
+        self.dead_time = 0.0;
+        self.stop_movement();
+        self.update_camera_plane();
+    }
+
+    pub fn set_fov(&mut self, fov: f32) {
+        self.fov = fov;
+        self.update_camera_plane();
+    }
+
+    ///The camera plane is perpendicular to the (normalized) direction, its length sets the field of view.
+    ///The shaders derive their vertical scale from it, which keeps the image aspect correct.
+    fn update_camera_plane(&mut self) {
+        let direction = self.transform.direction.normalized();
+        let half_width = (self.fov / 2.0).to_radians().tan();
+
+        self.transform.direction = direction;
+        self.transform.camera_plane = Vector2::new(direction.y, -direction.x) * half_width;
+    }
+
     pub fn copy_to_gpu(&self) {
         self.ssbo.update(&self.transform, 0);
     }
@@ -56,18 +178,96 @@ impl Player {
         self.transform.direction.y =
             old_dir_x * angle.sin() + self.transform.direction.y * angle.cos();
 
-        let old_plane_x = self.transform.camera_plane.x;
-        self.transform.camera_plane.x = self.transform.camera_plane.x * angle.cos()
-            - self.transform.camera_plane.y * angle.sin();
-        self.transform.camera_plane.y =
-            old_plane_x * angle.sin() + self.transform.camera_plane.y * angle.cos();
+        self.update_camera_plane();
+    }
+
+    pub fn rotate_by_mouse(&mut self, rel_mov: &Vector2<f32>, sensitivity: f32, delta_time: f32) {
+        self.rotate(rel_mov.x * ROTATION_SPEED * sensitivity * delta_time * -1.0);
+
+        self.transform.pitch = (self.transform.pitch
+            - rel_mov.y * PITCH_SPEED * sensitivity * delta_time)
+            .clamp(-MAX_PITCH, MAX_PITCH);
     }
 
-    pub fn rotate_by_mouse(&mut self, rel_mov: &Vector2<f32>, delta_time: f32) {
-        self.rotate(rel_mov.x * ROTATION_SPEED * delta_time * -1.0);
+    pub fn jump(&mut self) {
+        if self.on_ground() && !self.is_dead() {
+            self.vertical_velocity = JUMP_VELOCITY;
+        }
     }
 
-    pub fn update_position(&mut self, world: &World, delta_time: f32) {
+    pub fn start_crouch(&mut self) {
+        self.crouching = true;
+    }
+
+    pub fn end_crouch(&mut self) {
+        self.crouching = false;
+    }
+
+    fn ground_eye_height(&self) -> f32 {
+        if self.crouching {
+            CROUCH_EYE_HEIGHT
+        } else {
+            EYE_HEIGHT
+        }
+    }
+
+    pub fn on_ground(&self) -> bool {
+        self.vertical_velocity == 0.0 && self.transform.eye_height <= EYE_HEIGHT
+    }
+
+    fn update_height(&mut self, delta_time: f32) {
+        let ground = self.ground_eye_height();
+
+        if self.vertical_velocity != 0.0 || self.transform.eye_height > EYE_HEIGHT {
+            self.vertical_velocity -= GRAVITY * delta_time;
+            self.transform.eye_height += self.vertical_velocity * delta_time;
+
+            if self.transform.eye_height <= ground {
+                self.transform.eye_height = ground;
+                self.vertical_velocity = 0.0;
+            }
+        } else if self.transform.eye_height < ground {
+            self.transform.eye_height =
+                (self.transform.eye_height + CROUCH_SPEED * delta_time).min(ground);
+        } else {
+            self.transform.eye_height =
+                (self.transform.eye_height - CROUCH_SPEED * delta_time).max(ground);
+        }
+    }
+
+    fn movement_speed(&self) -> f32 {
+        if self.crouching {
+            MOVEMENT_SPEED * CROUCH_MOVEMENT_FACTOR
+        } else {
+            MOVEMENT_SPEED
+        }
+    }
+
+    ///Movement and the effects of damage, dead players only fall over
+    pub fn update(&mut self, world: &World, delta_time: f32) {
+        self.flash = (self.flash - delta_time / FLASH_FADE_TIME).max(0.0);
+
+        if self.is_dead() {
+            self.dead_time += delta_time;
+            self.fall(delta_time);
+        } else {
+            self.update_position(world, delta_time);
+        }
+    }
+
+    fn fall(&mut self, delta_time: f32) {
+        if self.transform.eye_height <= DEAD_EYE_HEIGHT {
+            return;
+        }
+
+        self.vertical_velocity -= GRAVITY * delta_time;
+        self.transform.eye_height =
+            (self.transform.eye_height + self.vertical_velocity * delta_time).max(DEAD_EYE_HEIGHT);
+    }
+
+    fn update_position(&mut self, world: &World, delta_time: f32) {
+        self.update_height(delta_time);
+
         if self.movement[FORWARDS] {
             self.step_forward(world, delta_time);
         }
@@ -94,62 +294,117 @@ impl Player {
     }
 
     fn step_forward(&mut self, world: &World, delta_time: f32) {
-        let future_x =
-            self.transform.position.x + self.transform.direction.x * MOVEMENT_SPEED * delta_time;
-        if *world.at(future_x as u32, self.transform.position.y as u32) == 0 {
-            self.transform.position.x = future_x;
-        }
-
-        let future_y =
-            self.transform.position.y + self.transform.direction.y * MOVEMENT_SPEED * delta_time;
-        if *world.at(self.transform.position.x as u32, future_y as u32) == 0 {
-            self.transform.position.y = future_y;
-        }
+        let offset = self.transform.direction * self.movement_speed() * delta_time;
+        self.slide(world, offset);
     }
 
     fn step_backward(&mut self, world: &World, delta_time: f32) {
-        let future_x =
-            self.transform.position.x - self.transform.direction.x * MOVEMENT_SPEED * delta_time;
-        if *world.at(future_x as u32, self.transform.position.y as u32) == 0 {
-            self.transform.position.x = future_x;
-        }
-
-        let future_y =
-            self.transform.position.y - self.transform.direction.y * MOVEMENT_SPEED * delta_time;
-        if *world.at(self.transform.position.x as u32, future_y as u32) == 0 {
-            self.transform.position.y = future_y;
-        }
+        let offset = -self.transform.direction * self.movement_speed() * delta_time;
+        self.slide(world, offset);
     }
 
     fn step_right(&mut self, world: &World, delta_time: f32) {
-        let future_x =
-            self.transform.position.x + self.transform.camera_plane.x * MOVEMENT_SPEED * delta_time;
-        if *world.at(future_x as u32, self.transform.position.y as u32) == 0 {
-            self.transform.position.x = future_x;
-        }
-
-        let future_y =
-            self.transform.position.y + self.transform.camera_plane.y * MOVEMENT_SPEED * delta_time;
-        if *world.at(self.transform.position.x as u32, future_y as u32) == 0 {
-            self.transform.position.y = future_y;
-        }
+        let right = self.transform.camera_plane.normalized();
+        self.slide(world, right * self.movement_speed() * delta_time);
     }
 
     fn step_left(&mut self, world: &World, delta_time: f32) {
-        let future_x =
-            self.transform.position.x - self.transform.camera_plane.x * MOVEMENT_SPEED * delta_time;
-        if *world.at(future_x as u32, self.transform.position.y as u32) == 0 {
-            self.transform.position.x = future_x;
+        let right = self.transform.camera_plane.normalized();
+        self.slide(world, -right * self.movement_speed() * delta_time);
+    }
+
+    ///Moves along both axes on their own, so the player slides along whatever blocks the other one
+    fn slide(&mut self, world: &World, offset: Vector2<f32>) {
+        let position = self.transform.position;
+        let future = Vector2::new(position.x + offset.x, position.y);
+        if !world.blocks_step(position, future) {
+            self.transform.position.x = future.x;
         }
 
-        let future_y =
-            self.transform.position.y - self.transform.camera_plane.y * MOVEMENT_SPEED * delta_time;
-        if *world.at(self.transform.position.x as u32, future_y as u32) == 0 {
-            self.transform.position.y = future_y;
+        let position = self.transform.position;
+        let future = Vector2::new(position.x, position.y + offset.y);
+        if !world.blocks_step(position, future) {
+            self.transform.position.y = future.y;
         }
     }
 
+    ///Releases all movement keys, e.g. when a menu takes over the input
+    pub fn stop_movement(&mut self) {
+        self.movement = [false, false, false, false];
+        self.crouching = false;
+    }
+
     pub fn position(&self) -> &Vector2<f32> {
         return &self.transform.position;
     }
+
+    pub fn direction(&self) -> &Vector2<f32> {
+        &self.transform.direction
+    }
+
+    ///Used by everything that hurts the player, armor takes part of the damage
+    pub fn damage(&mut self, amount: u32) {
+        if self.is_dead() {
+            return;
+        }
+
+        let lost = self.inventory.damage(amount);
+        self.flash = (self.flash + lost as f32 / FLASH_DAMAGE).min(1.0);
+
+        if self.is_dead() {
+            self.dead_time = 0.0;
+            self.vertical_velocity = 0.0;
+            self.stop_movement();
+        }
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.inventory.health() == 0
+    }
+
+    ///Whether the death animation is over
+    pub fn can_respawn(&self) -> bool {
+        self.is_dead() && self.dead_time >= DEATH_DURATION
+    }
+
+    ///Starts over with a fresh inventory that only keeps the score, `place` moves the player to the start
+    pub fn revive(&mut self) {
+        self.inventory.revive();
+        self.flash = 0.0;
+        self.dead_time = 0.0;
+    }
+
+    ///Strength of the red flash after taking damage, 0.0 to 1.0
+    pub fn flash(&self) -> f32 {
+        self.flash
+    }
+
+    pub fn health(&self) -> u32 {
+        self.inventory.health()
+    }
+
+    pub fn ammo(&self) -> u32 {
+        self.inventory.ammo(AmmoType::Bullets)
+    }
+
+    pub fn ammo_of(&self, ammo: AmmoType) -> u32 {
+        self.inventory.ammo(ammo)
+    }
+
+    ///Takes one round, returns false if there is none left
+    pub fn use_ammo(&mut self, ammo: AmmoType) -> bool {
+        self.inventory.use_ammo(ammo)
+    }
+
+    pub fn score(&self) -> u32 {
+        self.inventory.score()
+    }
+
+    pub fn inventory(&self) -> &Inventory {
+        &self.inventory
+    }
+
+    pub fn inventory_mut(&mut self) -> &mut Inventory {
+        &mut self.inventory
+    }
 }
@@ -0,0 +1,128 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+use super::entities::EntityState;
+use super::game::LevelStats;
+use super::player::PlayerState;
+use super::sprites::SpriteState;
+use super::util::paths;
+use super::weapons::ArsenalState;
+
+const SAVE_DIR: &str = "saves";
+
+//Bump whenever the format changes and add a step to `SaveGame::migrate`
+const SAVE_VERSION: u32 = 2;
+
+///Slot 0 is written by quicksave, the others are picked in the menu
+pub const QUICKSAVE_SLOT: u32 = 0;
+pub const SLOT_COUNT: u32 = 6;
+
+///Snapshot of a running game. Loading goes through `World::load` and applies the
+///changes on top, so only what differs from the map file has to be stored
+#[derive(Serialize, Deserialize)]
+pub struct SaveGame {
+    version: u32,
+    pub map: String,
+    pub player: PlayerState,
+    #[serde(default)]
+    pub entities: Vec<EntityState>,
+    //Version 1 stored bare sprites, they are turned into entities when loading
+    #[serde(default, skip_serializing)]
+    sprites: Vec<SpriteState>,
+    //Cells that changed since the map was loaded as (x, y, tile)
+    pub tiles: Vec<(u32, u32, u32)>,
+    pub stats: LevelStats,
+    //Secret cells found so far, saves from before secrets have none
+    #[serde(default)]
+    pub secrets: Vec<(u32, u32)>,
+    //Saves from before weapons keep the ones a new game starts with
+    #[serde(default)]
+    pub weapons: Option<ArsenalState>,
+}
+
+impl SaveGame {
+    pub fn new(
+        map: String,
+        player: PlayerState,
+        entities: Vec<EntityState>,
+        tiles: Vec<(u32, u32, u32)>,
+        stats: LevelStats,
+        secrets: Vec<(u32, u32)>,
+        weapons: ArsenalState,
+    ) -> SaveGame {
+        SaveGame {
+            version: SAVE_VERSION,
+            map,
+            player,
+            entities,
+            sprites: Vec::new(),
+            tiles,
+            stats,
+            secrets,
+            weapons: Some(weapons),
+        }
+    }
+
+    fn path(slot: u32) -> Option<PathBuf> {
+        paths::config_dir().map(|dir| dir.join(SAVE_DIR).join(format!("slot{}.ron", slot)))
+    }
+
+    pub fn save(&self, slot: u32) -> Result<(), Box<dyn std::error::Error>> {
+        let path = Self::path(slot).ok_or("Couldn't determine the users config directory")?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let content = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::new())?;
+        fs::write(&path, content)?;
+
+        Ok(())
+    }
+
+    pub fn load(slot: u32) -> Result<SaveGame, Box<dyn std::error::Error>> {
+        let path = Self::path(slot).ok_or("Couldn't determine the users config directory")?;
+        let file_content = fs::read_to_string(path)?;
+
+        let mut save: SaveGame = ron::from_str(&file_content)?;
+        save.migrate()?;
+
+        Ok(save)
+    }
+
+    fn migrate(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.version > SAVE_VERSION {
+            return Err(format!(
+                "Save game is version {}, newer than the supported version {}",
+                self.version, SAVE_VERSION
+            )
+            .into());
+        }
+
+        //Sprites were spawned by their template name, which is the entity name now
+        if self.version < 2 {
+            self.entities = self
+                .sprites
+                .drain(..)
+                .map(|sprite| EntityState {
+                    name: sprite.template.clone(),
+                    position: sprite.position,
+                    direction: sprite.direction,
+                    health: None,
+                    faction: None,
+                    sprite: Some(sprite),
+                })
+                .collect();
+        }
+
+        self.version = SAVE_VERSION;
+        Ok(())
+    }
+
+    ///Map of the save game in every slot, None for empty or unreadable ones
+    pub fn slots() -> Vec<Option<String>> {
+        (0..SLOT_COUNT)
+            .map(|slot| Self::load(slot).ok().map(|save| save.map))
+            .collect()
+    }
+}
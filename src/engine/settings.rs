@@ -1,64 +1,681 @@
-use crate::gpu::{self, SSBO};
+use crate::gpu::{self, ISSBO, SSBO};
 
-use crate::gpu::ISSBO;
-use serde::Deserialize;
+use super::util::paths;
+use super::I18n;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::fs;
+use std::path::{Path, PathBuf};
 
 const RES_SETTINGS: &str = "./res/settings.ron";
+const USER_SETTINGS: &str = "settings.ron";
 
+//Bump whenever the meaning of a setting changes and add a step to `SettingsLayer::migrate`
+const SETTINGS_VERSION: u32 = 2;
+
+///Settings are layered: built in defaults, the shipped `res/settings.ron`,
+///the users own settings file and finally the command line.
+///Changes made at runtime only ever end up in the users file.
 pub struct Settings {
-    core: SettingsCore,
-    _ssbo: SSBO,
+    values: UserSettings,
+    user_layer: SettingsLayer,
+    modified: bool,
+    //Size of the image everything is rendered at, framebuffer size scaled by render_scale
+    render_resolution: (u32, u32),
+    //Rows at the bottom of the image reserved for the status bar
+    status_bar_height: u32,
+    gpu: GpuSettings,
+    ssbo: SSBO,
 }
 
-#[derive(Deserialize)]
+///The settings block shared with the shaders at binding 1, has to match their std430 declaration:
+///```glsl
+///layout(std430, binding = 1) buffer Settings {
+///    uint resolution_x;
+///    uint resolution_y;
+///    uint view_offset_y;
+///} settings;
+///```
 #[repr(C)]
-pub struct SettingsCore {
+struct GpuSettings {
+    //Size of the 3D view, the render resolution minus the status bar
+    resolution_x: u32,
+    resolution_y: u32,
+    //First image row of the 3D view
+    view_offset_y: u32,
+}
+
+impl ISSBO for GpuSettings {}
+
+const _: () = assert!(std::mem::size_of::<GpuSettings>() == 12);
+const _: () = assert!(std::mem::offset_of!(GpuSettings, resolution_x) == 0);
+const _: () = assert!(std::mem::offset_of!(GpuSettings, resolution_y) == 4);
+const _: () = assert!(std::mem::offset_of!(GpuSettings, view_offset_y) == 8);
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum WindowMode {
+    Windowed,
+    //Fullscreen window using the monitors current video mode
+    Borderless,
+    //Fullscreen with the configured resolution as video mode
+    Fullscreen,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+///Everything the user can configure, never uploaded to the gpu
+pub struct UserSettings {
     resolution: (u32, u32),
     language: String,
+    //Horizontal field of view in degrees
+    fov: f32,
+    window_mode: WindowMode,
+    //Fraction of the window resolution the world is rendered at before being upscaled
+    render_scale: f32,
+    vsync: bool,
+    mouse_sensitivity: f32,
+    difficulty: Difficulty,
+    //Volumes range from 0.0 (muted) to 1.0
+    master_volume: f32,
+    music_volume: f32,
+    effects_volume: f32,
 }
 
-impl ISSBO for SettingsCore {}
+pub const MIN_FOV: f32 = 40.0;
+pub const MAX_FOV: f32 = 130.0;
 
-impl Settings {
-    pub fn load() -> Settings {
-        let core = match fs::read_to_string(RES_SETTINGS) {
-            Ok(file_content) => match ron::from_str::<SettingsCore>(&file_content) {
-                Ok(settings) => settings,
-                Err(e) => {
-                    eprintln!("Couldn't load settings, using defaults: {}", e.to_string());
-                    Settings::default()
+const MIN_RESOLUTION: (u32, u32) = (320, 200);
+const MAX_RESOLUTION: u32 = 16384;
+const MIN_RENDER_SCALE: f32 = 0.1;
+const MAX_RENDER_SCALE: f32 = 2.0;
+const MIN_MOUSE_SENSITIVITY: f32 = 0.05;
+const MAX_MOUSE_SENSITIVITY: f32 = 10.0;
+
+//Field of view of the fixed camera plane used before version 2
+const LEGACY_FOV: f32 = 83.0;
+
+///One layer of settings, files only have to contain the values they change
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename = "SettingsCore")]
+struct SettingsLayer {
+    //Files without a version predate versioning
+    #[serde(default = "legacy_version")]
+    version: u32,
+
+    #[serde(
+        default,
+        deserialize_with = "some",
+        serialize_with = "unwrap_some",
+        skip_serializing_if = "Option::is_none"
+    )]
+    resolution: Option<(u32, u32)>,
+    #[serde(
+        default,
+        deserialize_with = "some",
+        serialize_with = "unwrap_some",
+        skip_serializing_if = "Option::is_none"
+    )]
+    language: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "some",
+        serialize_with = "unwrap_some",
+        skip_serializing_if = "Option::is_none"
+    )]
+    fov: Option<f32>,
+    #[serde(
+        default,
+        deserialize_with = "some",
+        serialize_with = "unwrap_some",
+        skip_serializing_if = "Option::is_none"
+    )]
+    window_mode: Option<WindowMode>,
+    #[serde(
+        default,
+        deserialize_with = "some",
+        serialize_with = "unwrap_some",
+        skip_serializing_if = "Option::is_none"
+    )]
+    render_scale: Option<f32>,
+    #[serde(
+        default,
+        deserialize_with = "some",
+        serialize_with = "unwrap_some",
+        skip_serializing_if = "Option::is_none"
+    )]
+    vsync: Option<bool>,
+    #[serde(
+        default,
+        deserialize_with = "some",
+        serialize_with = "unwrap_some",
+        skip_serializing_if = "Option::is_none"
+    )]
+    mouse_sensitivity: Option<f32>,
+    #[serde(
+        default,
+        deserialize_with = "some",
+        serialize_with = "unwrap_some",
+        skip_serializing_if = "Option::is_none"
+    )]
+    difficulty: Option<Difficulty>,
+    #[serde(
+        default,
+        deserialize_with = "some",
+        serialize_with = "unwrap_some",
+        skip_serializing_if = "Option::is_none"
+    )]
+    master_volume: Option<f32>,
+    #[serde(
+        default,
+        deserialize_with = "some",
+        serialize_with = "unwrap_some",
+        skip_serializing_if = "Option::is_none"
+    )]
+    music_volume: Option<f32>,
+    #[serde(
+        default,
+        deserialize_with = "some",
+        serialize_with = "unwrap_some",
+        skip_serializing_if = "Option::is_none"
+    )]
+    effects_volume: Option<f32>,
+}
+
+fn legacy_version() -> u32 {
+    1
+}
+
+//Lets optional fields be written as plain values instead of Some(...)
+fn some<'de, D: Deserializer<'de>, T: Deserialize<'de>>(
+    deserializer: D,
+) -> Result<Option<T>, D::Error> {
+    T::deserialize(deserializer).map(Some)
+}
+
+fn unwrap_some<S: Serializer, T: Serialize>(
+    value: &Option<T>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    value.as_ref().unwrap().serialize(serializer)
+}
+
+impl SettingsLayer {
+    fn empty() -> SettingsLayer {
+        SettingsLayer {
+            version: SETTINGS_VERSION,
+            ..Default::default()
+        }
+    }
+
+    fn load(path: &Path) -> Option<SettingsLayer> {
+        let file_content = match fs::read_to_string(path) {
+            Ok(file_content) => file_content,
+            Err(e) => {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    eprintln!("Couldn't read settings {:?}, ignoring them: {}", path, e);
                 }
-            },
+                return None;
+            }
+        };
+
+        match ron::from_str::<SettingsLayer>(&file_content) {
+            Ok(mut layer) => {
+                layer.migrate(path);
+                Some(layer)
+            }
             Err(e) => {
-                eprintln!("Couldn't load settings, using defaults: {}", e.to_string());
-                Settings::default()
+                eprintln!("Couldn't parse settings {:?}, ignoring them: {}", path, e);
+                None
+            }
+        }
+    }
+
+    fn migrate(&mut self, path: &Path) {
+        if self.version > SETTINGS_VERSION {
+            eprintln!(
+                "Settings {:?} are version {}, newer than the supported version {}",
+                path, self.version, SETTINGS_VERSION
+            );
+            return;
+        }
+
+        if self.version < SETTINGS_VERSION {
+            eprintln!(
+                "Migrating settings {:?} from version {} to {}",
+                path, self.version, SETTINGS_VERSION
+            );
+        }
+
+        //Version 1 had no field of view setting, keep the look of the old fixed camera plane
+        if self.version < 2 && self.fov.is_none() {
+            self.fov = Some(LEGACY_FOV);
+        }
+
+        self.version = SETTINGS_VERSION;
+    }
+
+    ///Parses overrides like `--resolution 1280x720 --language de_DE`, invalid arguments are skipped
+    fn from_args(args: &[String]) -> SettingsLayer {
+        let mut layer = SettingsLayer::empty();
+
+        const FLAGS: [&str; 7] = [
+            "--resolution",
+            "--language",
+            "--fov",
+            "--window-mode",
+            "--render-scale",
+            "--vsync",
+            "--difficulty",
+        ];
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            //Unknown flags don't take a value, so the next argument is read as a flag again
+            if !FLAGS.contains(&arg.as_str()) {
+                eprintln!("Unknown command line argument '{}'", arg);
+                continue;
+            }
+
+            let value = match args.next() {
+                Some(value) => value,
+                None => {
+                    eprintln!("Missing value for command line argument '{}'", arg);
+                    break;
+                }
+            };
+
+            let result = match arg.as_str() {
+                "--resolution" => parse_resolution(value).map(|v| layer.resolution = Some(v)),
+                "--language" => {
+                    layer.language = Some(value.clone());
+                    Ok(())
+                }
+                "--fov" => value.parse().map(|v| layer.fov = Some(v)).map_err(|_| ()),
+                "--window-mode" => parse_window_mode(value).map(|v| layer.window_mode = Some(v)),
+                "--render-scale" => value
+                    .parse()
+                    .map(|v| layer.render_scale = Some(v))
+                    .map_err(|_| ()),
+                "--vsync" => value.parse().map(|v| layer.vsync = Some(v)).map_err(|_| ()),
+                "--difficulty" => parse_difficulty(value).map(|v| layer.difficulty = Some(v)),
+                _ => unreachable!(),
+            };
+
+            if result.is_err() {
+                eprintln!(
+                    "Invalid value '{}' for command line argument '{}'",
+                    value, arg
+                );
+            }
+        }
+
+        layer
+    }
+
+    fn apply(&self, values: &mut UserSettings) {
+        if let Some(resolution) = self.resolution {
+            values.resolution = resolution;
+        }
+        if let Some(language) = &self.language {
+            values.language = language.clone();
+        }
+        if let Some(fov) = self.fov {
+            values.fov = fov;
+        }
+        if let Some(window_mode) = self.window_mode {
+            values.window_mode = window_mode;
+        }
+        if let Some(render_scale) = self.render_scale {
+            values.render_scale = render_scale;
+        }
+        if let Some(vsync) = self.vsync {
+            values.vsync = vsync;
+        }
+        if let Some(mouse_sensitivity) = self.mouse_sensitivity {
+            values.mouse_sensitivity = mouse_sensitivity;
+        }
+        if let Some(difficulty) = self.difficulty {
+            values.difficulty = difficulty;
+        }
+        if let Some(master_volume) = self.master_volume {
+            values.master_volume = master_volume;
+        }
+        if let Some(music_volume) = self.music_volume {
+            values.music_volume = music_volume;
+        }
+        if let Some(effects_volume) = self.effects_volume {
+            values.effects_volume = effects_volume;
+        }
+    }
+
+    ///Replaces the set values with the validated ones, so invalid values aren't saved again
+    fn take_validated(&mut self, values: &UserSettings) {
+        self.resolution = self.resolution.map(|_| values.resolution);
+        self.language = self.language.as_ref().map(|_| values.language.clone());
+        self.fov = self.fov.map(|_| values.fov);
+        self.render_scale = self.render_scale.map(|_| values.render_scale);
+        self.mouse_sensitivity = self.mouse_sensitivity.map(|_| values.mouse_sensitivity);
+        self.master_volume = self.master_volume.map(|_| values.master_volume);
+        self.music_volume = self.music_volume.map(|_| values.music_volume);
+        self.effects_volume = self.effects_volume.map(|_| values.effects_volume);
+    }
+}
+
+fn parse_resolution(value: &str) -> Result<(u32, u32), ()> {
+    let mut parts = value.split('x');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(width), Some(height), None) => Ok((
+            width.trim().parse().map_err(|_| ())?,
+            height.trim().parse().map_err(|_| ())?,
+        )),
+        _ => Err(()),
+    }
+}
+
+fn parse_window_mode(value: &str) -> Result<WindowMode, ()> {
+    match value.to_lowercase().as_str() {
+        "windowed" => Ok(WindowMode::Windowed),
+        "borderless" => Ok(WindowMode::Borderless),
+        "fullscreen" => Ok(WindowMode::Fullscreen),
+        _ => Err(()),
+    }
+}
+
+fn parse_difficulty(value: &str) -> Result<Difficulty, ()> {
+    match value.to_lowercase().as_str() {
+        "easy" => Ok(Difficulty::Easy),
+        "normal" => Ok(Difficulty::Normal),
+        "hard" => Ok(Difficulty::Hard),
+        _ => Err(()),
+    }
+}
+
+impl Settings {
+    ///`args` are the command line arguments, without the program name
+    pub fn load(args: &[String]) -> Settings {
+        let mut values = Settings::default();
+
+        if let Some(shipped) = SettingsLayer::load(Path::new(RES_SETTINGS)) {
+            shipped.apply(&mut values);
+        }
+
+        let mut user = match Self::user_path() {
+            Some(path) => SettingsLayer::load(&path).unwrap_or_else(SettingsLayer::empty),
+            None => {
+                eprintln!("Couldn't determine the users config directory, changes won't be saved");
+                SettingsLayer::empty()
             }
         };
+        user.apply(&mut values);
+
+        //Validate before the command line is applied, its values must not end up in the user layer
+        Self::validate(&mut values);
+        user.take_validated(&values);
+
+        SettingsLayer::from_args(args).apply(&mut values);
+
+        Self::validate(&mut values);
+
+        let render_resolution = Self::scale_resolution(values.resolution, values.render_scale);
 
         Settings {
-            core,
-            _ssbo: gpu::SSBO::null(),
+            values,
+            user_layer: user,
+            modified: false,
+            render_resolution,
+            status_bar_height: 0,
+            gpu: GpuSettings {
+                resolution_x: render_resolution.0,
+                resolution_y: render_resolution.1,
+                view_offset_y: 0,
+            },
+            ssbo: gpu::SSBO::null(),
         }
     }
 
-    fn default() -> SettingsCore {
-        SettingsCore {
+    fn default() -> UserSettings {
+        UserSettings {
             resolution: (800, 600),
             language: String::from("en_GB"),
+            fov: 75.0,
+            window_mode: WindowMode::Windowed,
+            render_scale: 1.0,
+            vsync: true,
+            mouse_sensitivity: 1.0,
+            difficulty: Difficulty::Normal,
+            master_volume: 1.0,
+            music_volume: 0.8,
+            effects_volume: 1.0,
+        }
+    }
+
+    fn user_path() -> Option<PathBuf> {
+        paths::config_dir().map(|dir| dir.join(USER_SETTINGS))
+    }
+
+    ///Out of range values are clamped, unknown languages replaced by the default one
+    fn validate(values: &mut UserSettings) {
+        let resolution = (
+            values.resolution.0.clamp(MIN_RESOLUTION.0, MAX_RESOLUTION),
+            values.resolution.1.clamp(MIN_RESOLUTION.1, MAX_RESOLUTION),
+        );
+        if resolution != values.resolution {
+            eprintln!(
+                "Setting 'resolution' is {:?} but has to be within {:?} and {:?}, using {:?}",
+                values.resolution,
+                MIN_RESOLUTION,
+                (MAX_RESOLUTION, MAX_RESOLUTION),
+                resolution
+            );
+            values.resolution = resolution;
+        }
+
+        values.fov = Self::validate_range("fov", values.fov, MIN_FOV, MAX_FOV);
+        values.render_scale = Self::validate_range(
+            "render_scale",
+            values.render_scale,
+            MIN_RENDER_SCALE,
+            MAX_RENDER_SCALE,
+        );
+        values.mouse_sensitivity = Self::validate_range(
+            "mouse_sensitivity",
+            values.mouse_sensitivity,
+            MIN_MOUSE_SENSITIVITY,
+            MAX_MOUSE_SENSITIVITY,
+        );
+        values.master_volume =
+            Self::validate_range("master_volume", values.master_volume, 0.0, 1.0);
+        values.music_volume = Self::validate_range("music_volume", values.music_volume, 0.0, 1.0);
+        values.effects_volume =
+            Self::validate_range("effects_volume", values.effects_volume, 0.0, 1.0);
+
+        if !I18n::is_available(&values.language) {
+            let default = Self::default().language;
+            eprintln!(
+                "Setting 'language' is '{}' but there is no such translation, using '{}'",
+                values.language, default
+            );
+            values.language = default;
         }
     }
 
+    fn validate_range(name: &str, value: f32, min: f32, max: f32) -> f32 {
+        let clamped = if value.is_nan() {
+            min
+        } else {
+            value.clamp(min, max)
+        };
+        if clamped != value {
+            eprintln!(
+                "Setting '{}' is {} but has to be within {}..={}, using {}",
+                name, value, min, max, clamped
+            );
+        }
+
+        clamped
+    }
+
+    ///Writes everything changed by the user to the users settings file, the shipped file is never touched
+    pub fn save(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let path = Self::user_path().ok_or("Couldn't determine the users config directory")?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let content = ron::ser::to_string_pretty(&self.user_layer, ron::ser::PrettyConfig::new())?;
+        fs::write(&path, content)?;
+
+        self.modified = false;
+        Ok(())
+    }
+
+    ///Whether there are changes which haven't been saved yet
+    pub fn modified(&self) -> bool {
+        self.modified
+    }
+
     pub fn copy_to_gpu(&mut self) {
-        let ssbo = gpu::SSBO::from(1, &self.core, gl::STATIC_DRAW);
-        self._ssbo = ssbo;
+        if self.ssbo.id == 0 {
+            self.ssbo = gpu::SSBO::from(1, &self.gpu, gl::DYNAMIC_DRAW);
+        } else {
+            self.ssbo.update(&self.gpu, 0);
+        }
+    }
+
+    ///Has to be called whenever the windows framebuffer changes its size
+    pub fn resize(&mut self, framebuffer_size: (u32, u32)) {
+        self.render_resolution = Self::scale_resolution(framebuffer_size, self.values.render_scale);
+        self.update_view();
+    }
+
+    ///Reserves rows at the bottom of the image, the 3D view shrinks accordingly
+    pub fn set_status_bar_height(&mut self, height: u32) {
+        self.status_bar_height = height;
+        self.update_view();
+    }
+
+    fn update_view(&mut self) {
+        //Always leave at least one row for the 3D view
+        let bar = self
+            .status_bar_height
+            .min(self.render_resolution.1.saturating_sub(1));
+
+        self.gpu.resolution_x = self.render_resolution.0;
+        self.gpu.resolution_y = self.render_resolution.1 - bar;
+        self.gpu.view_offset_y = bar;
+        self.copy_to_gpu();
+    }
+
+    fn scale_resolution(resolution: (u32, u32), scale: f32) -> (u32, u32) {
+        (
+            ((resolution.0 as f32 * scale) as u32).max(1),
+            ((resolution.1 as f32 * scale) as u32).max(1),
+        )
+    }
+
+    pub fn render_resolution(&self) -> (u32, u32) {
+        self.render_resolution
+    }
+
+    ///Size of the 3D view within the rendered image
+    pub fn view_resolution(&self) -> (u32, u32) {
+        (self.gpu.resolution_x, self.gpu.resolution_y)
+    }
+
+    pub fn window_mode(&self) -> WindowMode {
+        self.values.window_mode
+    }
+
+    pub fn difficulty(&self) -> Difficulty {
+        self.values.difficulty
     }
 
     pub fn resolution(&self) -> &(u32, u32) {
-        return &self.core.resolution;
+        return &self.values.resolution;
     }
 
     pub fn language(&self) -> &String {
-        return &self.core.language;
+        return &self.values.language;
+    }
+
+    pub fn fov(&self) -> f32 {
+        self.values.fov
+    }
+
+    pub fn vsync(&self) -> bool {
+        self.values.vsync
+    }
+
+    pub fn mouse_sensitivity(&self) -> f32 {
+        self.values.mouse_sensitivity
+    }
+
+    pub fn master_volume(&self) -> f32 {
+        self.values.master_volume
+    }
+
+    pub fn music_volume(&self) -> f32 {
+        self.values.music_volume
+    }
+
+    pub fn effects_volume(&self) -> f32 {
+        self.values.effects_volume
+    }
+
+    pub fn set_fov(&mut self, fov: f32) {
+        self.values.fov = fov.clamp(MIN_FOV, MAX_FOV);
+        self.user_layer.fov = Some(self.values.fov);
+        self.modified = true;
+    }
+
+    ///Window resolution, takes effect once the window is resized
+    pub fn set_resolution(&mut self, resolution: (u32, u32)) {
+        self.values.resolution = (
+            resolution.0.clamp(MIN_RESOLUTION.0, MAX_RESOLUTION),
+            resolution.1.clamp(MIN_RESOLUTION.1, MAX_RESOLUTION),
+        );
+        self.user_layer.resolution = Some(self.values.resolution);
+        self.modified = true;
+    }
+
+    ///Unavailable languages are ignored
+    pub fn set_language(&mut self, language: &str) {
+        if !I18n::is_available(language) {
+            eprintln!("Language '{}' is not available", language);
+            return;
+        }
+
+        self.values.language = language.to_owned();
+        self.user_layer.language = Some(self.values.language.clone());
+        self.modified = true;
+    }
+
+    pub fn set_mouse_sensitivity(&mut self, sensitivity: f32) {
+        self.values.mouse_sensitivity =
+            sensitivity.clamp(MIN_MOUSE_SENSITIVITY, MAX_MOUSE_SENSITIVITY);
+        self.user_layer.mouse_sensitivity = Some(self.values.mouse_sensitivity);
+        self.modified = true;
+    }
+
+    pub fn set_master_volume(&mut self, volume: f32) {
+        self.values.master_volume = volume.clamp(0.0, 1.0);
+        self.user_layer.master_volume = Some(self.values.master_volume);
+        self.modified = true;
+    }
+
+    pub fn set_music_volume(&mut self, volume: f32) {
+        self.values.music_volume = volume.clamp(0.0, 1.0);
+        self.user_layer.music_volume = Some(self.values.music_volume);
+        self.modified = true;
+    }
+
+    pub fn set_effects_volume(&mut self, volume: f32) {
+        self.values.effects_volume = volume.clamp(0.0, 1.0);
+        self.user_layer.effects_volume = Some(self.values.effects_volume);
+        self.modified = true;
     }
 }
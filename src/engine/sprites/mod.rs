@@ -3,7 +3,7 @@ mod spriteasset;
 mod spriteregistry;
 mod tilesheet;
 
-pub use sprite::{Sprite, SpriteTemplate};
+pub use sprite::{Sprite, SpriteOverrides, SpriteState, SpriteTemplate};
 pub use spriteasset::SpriteAsset;
 pub use spriteregistry::SpriteRegistry;
 pub use tilesheet::Tilesheet;
@@ -1,4 +1,14 @@
 use crate::{engine::util::Vector2, gpu::ISSBO};
+use serde::{Deserialize, Serialize};
+
+use super::spriteasset::{Anchor, SpriteAsset};
+use super::tilesheet::Tilesheet;
+
+//Has to match the flags in sprites/preprocess.glsl and sprites/compute.glsl
+const SPRITE_BLOCKING: u32 = 1;
+const SPRITE_SHOOTABLE: u32 = 2;
+const SPRITE_FIXED: u32 = 4;
+const SPRITE_CEILING: u32 = 8;
 
 ///Layout(linear on gpu)
 ///N: Number of different animation sprites per view-angle
@@ -23,6 +33,39 @@ pub struct Sprite {
 
 impl ISSBO for Sprite {}
 
+///What is kept of a sprite instance in save games
+#[derive(Serialize, Deserialize)]
+pub struct SpriteState {
+    pub template: String,
+    pub position: Vector2<f32>,
+    pub direction: Vector2<f32>,
+    pub animation_index: i32,
+    pub animation_tick: i32,
+    //Missing in saves from before sprites could be resized
+    #[serde(default)]
+    pub scale: Option<(f32, f32)>,
+    #[serde(default)]
+    pub offset: Option<f32>,
+}
+
+impl SpriteState {
+    pub fn overrides(&self) -> SpriteOverrides {
+        SpriteOverrides {
+            scale: self.scale,
+            offset: self.offset,
+        }
+    }
+}
+
+///Changes to the size and height of a single sprite, the template has the rest
+#[derive(Deserialize, Clone, Default)]
+pub struct SpriteOverrides {
+    #[serde(default)]
+    pub scale: Option<(f32, f32)>,
+    #[serde(default)]
+    pub offset: Option<f32>,
+}
+
 impl Sprite {
     pub fn new(
         position: Vector2<f32>,
@@ -41,7 +84,11 @@ impl Sprite {
 
     pub fn move_by(&mut self, speed: f32, delta_time: f32) {
         self.position += self.direction * speed * delta_time;
+        self.tick_animation();
+    }
 
+    ///Advances the walking animation by one tick
+    pub fn tick_animation(&mut self) {
         self.animation_tick += 1;
         if self.animation_tick != self.animation_tick_period {
             return;
@@ -60,6 +107,63 @@ impl Sprite {
         &self.position
     }
 
+    ///Frames past the last one of the template are clamped
+    pub fn set_frame(&mut self, frame: i32) {
+        self.animation_index = frame.clamp(0, self.template.animation_count);
+        self.animation_tick = 0;
+    }
+
+    pub fn set_position(&mut self, position: Vector2<f32>) {
+        self.position = position;
+    }
+
+    pub fn set_direction(&mut self, direction: Vector2<f32>) {
+        self.direction = direction;
+    }
+
+    pub fn template(&self) -> &SpriteTemplate {
+        &self.template
+    }
+
+    pub fn state(&self, template: &str) -> SpriteState {
+        SpriteState {
+            template: template.to_owned(),
+            position: self.position,
+            direction: self.direction,
+            animation_index: self.animation_index,
+            animation_tick: self.animation_tick,
+            scale: Some((self.template.scale_x, self.template.scale_y)),
+            offset: Some(self.template.offset),
+        }
+    }
+
+    ///Instances keep a copy of their template, overriding it doesn't touch other instances
+    pub fn apply(&mut self, overrides: &SpriteOverrides) {
+        if let Some((x, y)) = overrides.scale {
+            self.template.scale_x = x.max(0.0);
+            self.template.scale_y = y.max(0.0);
+        }
+        if let Some(offset) = overrides.offset {
+            self.template.offset = offset;
+        }
+    }
+
+    ///Width in wall units
+    pub fn width(&self) -> f32 {
+        self.template.width()
+    }
+
+    pub fn restore(&mut self, state: &SpriteState) {
+        self.position = state.position;
+        self.direction = state.direction;
+        self.animation_index = state
+            .animation_index
+            .clamp(0, self.template.animation_count);
+        self.animation_tick = state
+            .animation_tick
+            .clamp(0, self.animation_tick_period - 1);
+    }
+
     fn animating(&self) -> bool {
         self.animation_index > 0
     }
@@ -91,26 +195,71 @@ pub struct SpriteTemplate {
 
     tile_width: i32,
     tile_height: i32,
+
+    flags: u32,
+    scale_x: f32,
+    scale_y: f32,
+    offset: f32,
 }
 
 impl ISSBO for SpriteTemplate {}
 
 impl SpriteTemplate {
+    ///Columns are the idle frame and the animation, rows the view angles
     pub fn new(
         texture_base_index: i32,
-        animation_count: i32,
-        view_angle_count: i32,
-        tile_width: i32,
-        tile_height: i32,
+        tile_sheet: &Tilesheet,
+        asset: &SpriteAsset,
     ) -> SpriteTemplate {
+        let mut flags = 0;
+        if asset.blocking {
+            flags |= SPRITE_BLOCKING;
+        }
+        if asset.shootable {
+            flags |= SPRITE_SHOOTABLE;
+        }
+        if !asset.billboard {
+            flags |= SPRITE_FIXED;
+        }
+        if asset.anchor == Anchor::Ceiling {
+            flags |= SPRITE_CEILING;
+        }
+
         SpriteTemplate {
             texture_base_index,
-            animation_count,
-            view_angle_count,
-            tile_width,
-            tile_height,
+            animation_count: tile_sheet.columns() - 1,
+            view_angle_count: tile_sheet.rows(),
+            tile_width: tile_sheet.tile_width(),
+            tile_height: tile_sheet.tile_height(),
+            flags,
+            scale_x: asset.scale.0.max(0.0),
+            scale_y: asset.scale.1.max(0.0),
+            offset: asset.offset,
         }
     }
+
+    ///Width in wall units
+    pub fn width(&self) -> f32 {
+        self.scale_x * self.tile_width as f32 / self.tile_height.max(1) as f32
+    }
+
+    ///Instances of the same template share its textures, whatever they override
+    pub fn same_textures(&self, other: &SpriteTemplate) -> bool {
+        self.texture_base_index == other.texture_base_index
+    }
+
+    pub fn is_blocking(&self) -> bool {
+        self.flags & SPRITE_BLOCKING != 0
+    }
+
+    pub fn is_shootable(&self) -> bool {
+        self.flags & SPRITE_SHOOTABLE != 0
+    }
+
+    ///Fixed sprites are flat, standing across their direction
+    pub fn is_billboard(&self) -> bool {
+        self.flags & SPRITE_FIXED == 0
+    }
 }
 
 ///This struct is for retrieving intermediate sprite processing results
@@ -127,5 +276,7 @@ pub struct SpritePreprocessResult {
     pub draw_end_x: i32,
 
     sprite_screen_x: i32,
+    //Screen row of the bottom edge, before clamping to the view
+    sprite_bottom: i32,
     pub transform_y: f64,
 }
@@ -1,11 +1,48 @@
 use serde::Deserialize;
 use std::{ffi::OsString, fs};
 
+///Where sprites hang from, the other end is wherever their scale reaches
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+pub enum Anchor {
+    #[default]
+    Floor,
+    Ceiling,
+}
+
 #[derive(Deserialize)]
 pub struct SpriteAsset {
+    //Name of the template, taken from the file name of the asset
+    #[serde(skip)]
+    pub name: OsString,
     pub file: String,
     pub columns: u32,
     pub rows: u32,
+
+    //Stops the player, entities without a collision radius get one as wide as the sprite
+    #[serde(default)]
+    pub blocking: bool,
+    //Stops hitscan shots
+    #[serde(default)]
+    pub shootable: bool,
+    //Billboards turn to face the viewer, the others stand flat along their direction
+    #[serde(default = "billboard")]
+    pub billboard: bool,
+    #[serde(default)]
+    pub anchor: Anchor,
+    //Width and height, 1.0 is as high as a wall and as wide as the tiles are in proportion
+    #[serde(default = "scale")]
+    pub scale: (f32, f32),
+    //Wall units the sprite is moved up from its anchor, negative values move it down
+    #[serde(default)]
+    pub offset: f32,
+}
+
+fn billboard() -> bool {
+    true
+}
+
+fn scale() -> (f32, f32) {
+    (1.0, 1.0)
 }
 
 impl SpriteAsset {
@@ -14,6 +51,7 @@ impl SpriteAsset {
         let mut asset = ron::from_str::<SpriteAsset>(&file_content)?;
 
         let total_path = std::path::Path::new(path);
+        asset.name = total_path.file_stem().unwrap_or_default().to_os_string();
         let parent = total_path.parent().unwrap().to_str().unwrap().to_string();
         asset.file = parent + "/" + &asset.file;
 
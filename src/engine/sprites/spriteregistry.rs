@@ -25,16 +25,16 @@ pub struct SpriteRegistry {
 }
 
 impl SpriteRegistry {
+    ///Instances are created by the entities owning them, the registry starts out empty
     pub fn create(
         world_tile_sheet: &Tilesheet,
-        world_sprites: &Vec<(String, Vector2<f32>, f32)>,
         sprite_folder_path: &String,
         player: &Player,
     ) -> Result<SpriteRegistry, Box<dyn std::error::Error>> {
         //Sprites folder
         let sprite_files = Self::ron_files(sprite_folder_path)?;
-        let sprite_assets = Self::ron_to_sprite_asset(&sprite_files);
-        let tile_sheets = Self::assets_to_tilesheets(sprite_assets);
+        let mut sprite_assets = Self::ron_to_sprite_asset(&sprite_files);
+        let tile_sheets = Self::assets_to_tilesheets(&mut sprite_assets);
 
         let width = tile_sheets.max_width().max(world_tile_sheet.tile_width());
         let height = tile_sheets.max_height().max(world_tile_sheet.tile_height());
@@ -62,34 +62,23 @@ impl SpriteRegistry {
         let mut sprite_templates: HashMap<OsString, SpriteTemplate> = HashMap::new();
 
         let mut location = world_tile_sheet.tile_count();
-        for i in 0..tile_sheets.len() {
-            let sprite_name = Path::new(&sprite_files[i])
-                .file_stem()
-                .unwrap()
-                .to_os_string();
-
+        for (asset, tile_sheet) in sprite_assets.iter().zip(&tile_sheets) {
             sprite_templates.insert(
-                sprite_name,
-                SpriteTemplate::new(
-                    location,
-                    tile_sheets[i].columns() - 1,
-                    tile_sheets[i].rows(),
-                    tile_sheets[i].tile_width(),
-                    tile_sheets[i].tile_height(),
-                ),
+                asset.name.clone(),
+                SpriteTemplate::new(location, tile_sheet, asset),
             );
 
             sampler.update(
                 0,
                 0,
                 location,
-                tile_sheets[i].tile_width(),
-                tile_sheets[i].tile_height(),
-                tile_sheets[i].tile_count(),
-                tile_sheets[i].tile(0),
+                tile_sheet.tile_width(),
+                tile_sheet.tile_height(),
+                tile_sheet.tile_count(),
+                tile_sheet.tile(0),
             );
 
-            location += tile_sheets[i].tile_count();
+            location += tile_sheet.tile_count();
         }
 
         let sprites_ssbo = SSBO::empty(8, 0, gl::DYNAMIC_DRAW);
@@ -105,16 +94,6 @@ impl SpriteRegistry {
             sprite_instances: Vec::new(),
         };
 
-        let mut sprite_instances: Vec<Rc<RefCell<Sprite>>> = Vec::new();
-        for tup in world_sprites {
-            match registry.instantiate(&tup.0, &tup.1, tup.2) {
-                Some(sprite) => sprite_instances.push(sprite),
-                None => {}
-            }
-        }
-
-        registry.sprite_instances = sprite_instances;
-
         registry.update(player);
 
         Ok(registry)
@@ -180,6 +159,19 @@ impl SpriteRegistry {
         });
     }
 
+    ///Name of the template the sprite was instantiated from
+    pub fn template_name(&self, sprite: &Sprite) -> Option<&str> {
+        self.sprite_templates
+            .iter()
+            .find(|(_, template)| template.same_textures(sprite.template()))
+            .and_then(|(name, _)| name.to_str())
+    }
+
+    pub fn remove(&mut self, sprite: &Rc<RefCell<Sprite>>) {
+        self.sprite_instances
+            .retain(|instance| !Rc::ptr_eq(instance, sprite));
+    }
+
     pub fn count(&self) -> usize {
         self.sprite_instances.len()
     }
@@ -188,18 +180,23 @@ impl SpriteRegistry {
         Vector2::new(angle.to_radians().cos(), angle.to_radians().sin())
     }
 
-    fn assets_to_tilesheets(assets: Vec<SpriteAsset>) -> Vec<Tilesheet> {
+    ///Assets without a tilesheet are dropped, the rest stay in the order of their sheets
+    fn assets_to_tilesheets(assets: &mut Vec<SpriteAsset>) -> Vec<Tilesheet> {
         let mut tile_sheets: Vec<Tilesheet> = Vec::new();
-        for asset in assets {
-            match Tilesheet::from(&asset) {
-                Ok(sheet) => tile_sheets.push(sheet),
-                Err(e) => eprintln!(
+        assets.retain(|asset| match Tilesheet::from(asset) {
+            Ok(sheet) => {
+                tile_sheets.push(sheet);
+                true
+            }
+            Err(e) => {
+                eprintln!(
                     "Cant create tilesheet '{}' because '{}'",
                     asset.file,
                     e.to_string()
-                ),
+                );
+                false
             }
-        }
+        });
 
         tile_sheets
     }
@@ -221,6 +218,11 @@ impl SpriteRegistry {
     }
 
     fn ron_files(path: &String) -> Result<Vec<OsString>, Box<dyn std::error::Error>> {
+        //Maps without a sprites folder simply have no sprites
+        if !Path::new(path).is_dir() {
+            return Ok(Vec::new());
+        }
+
         let dir = fs::read_dir(path)?;
         let mut files: Vec<OsString> = Vec::new();
         for dir_entry in dir {
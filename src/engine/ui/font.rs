@@ -0,0 +1,79 @@
+use std::ffi::{c_void, OsString};
+
+use super::fontasset::{FontAsset, GlyphMetrics};
+use super::overlay::ATLAS_BINDING;
+use crate::gpu::TextureSampler;
+
+const RES_FONTS: &str = "./res/fonts/";
+
+///A bitmap font whose atlas lives on the GPU
+pub struct Font {
+    asset: FontAsset,
+    atlas_height: u32,
+    atlas: TextureSampler,
+}
+
+impl Font {
+    pub fn load(name: &str) -> Result<Font, Box<dyn std::error::Error>> {
+        let asset = FontAsset::load(&OsString::from(RES_FONTS.to_owned() + name + "/font.ron"))?;
+
+        //Flipped so that the rows match the framebuffer, which starts at the bottom
+        let img = image::imageops::flip_vertical(&image::open(&asset.file)?.to_rgba8());
+        let (width, height) = img.dimensions();
+        let pixels = img.into_raw();
+
+        let atlas = TextureSampler::from(
+            ATLAS_BINDING,
+            width as i32,
+            height as i32,
+            1,
+            pixels.as_ptr() as *const c_void,
+        );
+
+        Ok(Font {
+            asset,
+            atlas_height: height,
+            atlas,
+        })
+    }
+
+    pub fn glyph(&self, c: char) -> &GlyphMetrics {
+        self.asset
+            .glyphs
+            .get(&c)
+            .unwrap_or(&self.asset.glyphs[&self.asset.fallback])
+    }
+
+    ///Size of the text in pixels, lines are separated by '\n'
+    pub fn measure(&self, text: &str, scale: f32) -> (u32, u32) {
+        let mut width = 0;
+        let mut lines = 0;
+
+        for line in text.split('\n') {
+            let advance: i32 = line.chars().map(|c| self.glyph(c).advance).sum();
+            width = width.max(advance);
+            lines += 1;
+        }
+
+        (
+            (width as f32 * scale).ceil() as u32,
+            (lines as f32 * self.asset.line_height as f32 * scale).ceil() as u32,
+        )
+    }
+
+    pub fn line_height(&self) -> u32 {
+        self.asset.line_height
+    }
+
+    pub fn ascent(&self) -> u32 {
+        self.asset.ascent
+    }
+
+    pub fn atlas_height(&self) -> u32 {
+        self.atlas_height
+    }
+
+    pub fn texture(&self) -> &TextureSampler {
+        &self.atlas
+    }
+}
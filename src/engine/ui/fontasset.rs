@@ -0,0 +1,48 @@
+use serde::Deserialize;
+use std::{collections::HashMap, ffi::OsString, fs};
+
+///Where a glyph is in the atlas and how it sits on the baseline, all in pixels
+#[derive(Deserialize, Clone, Copy)]
+pub struct GlyphMetrics {
+    //Top left corner in the atlas image
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+
+    pub x_offset: i32,
+    //Distance from the baseline to the bottom of the glyph, up is positive
+    pub y_offset: i32,
+    pub advance: i32,
+}
+
+#[derive(Deserialize)]
+pub struct FontAsset {
+    pub file: String,
+    pub line_height: u32,
+    //Distance from the top of a line to the baseline
+    pub ascent: u32,
+    pub glyphs: HashMap<char, GlyphMetrics>,
+    //Drawn for characters the font has no glyph for
+    pub fallback: char,
+}
+
+impl FontAsset {
+    pub fn load(path: &OsString) -> Result<FontAsset, Box<dyn std::error::Error>> {
+        let file_content = fs::read_to_string(path)?;
+        let mut asset = ron::from_str::<FontAsset>(&file_content)?;
+
+        if !asset.glyphs.contains_key(&asset.fallback) {
+            return Err(format!(
+                "Font {:?} has no glyph for its fallback character '{}'",
+                path, asset.fallback
+            ))?;
+        }
+
+        let total_path = std::path::Path::new(path);
+        let parent = total_path.parent().unwrap().to_str().unwrap().to_string();
+        asset.file = parent + "/" + &asset.file;
+
+        Ok(asset)
+    }
+}
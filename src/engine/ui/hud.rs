@@ -0,0 +1,272 @@
+use serde::Deserialize;
+use std::ffi::OsString;
+use std::fs;
+
+use super::font::Font;
+use super::imagesheet::ImageSheet;
+use super::overlay::{Color, Overlay};
+use crate::engine::inventory::{self, Key};
+use crate::engine::player::Player;
+
+const RES_HUD: &str = "./res/hud/";
+
+#[derive(Deserialize, Clone, Copy)]
+pub enum HudValue {
+    Health,
+    Armor,
+    Ammo,
+    Score,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+///Positions and sizes are in layout units, relative to the top left of the status bar
+#[derive(Deserialize)]
+pub enum HudElement {
+    Panel {
+        position: (f32, f32),
+        size: (f32, f32),
+        color: Color,
+    },
+    Icon {
+        tile: u32,
+        position: (f32, f32),
+        size: (f32, f32),
+    },
+    Counter {
+        value: HudValue,
+        position: (f32, f32),
+        scale: f32,
+        color: Color,
+        align: Align,
+    },
+    //`count` consecutive tiles from healthy to dead
+    Face {
+        first_tile: u32,
+        count: u32,
+        position: (f32, f32),
+        size: (f32, f32),
+    },
+    LevelName {
+        position: (f32, f32),
+        scale: f32,
+        color: Color,
+        align: Align,
+    },
+    //Only shown while the player holds the key
+    Key {
+        key: Key,
+        tile: u32,
+        position: (f32, f32),
+        size: (f32, f32),
+    },
+}
+
+#[derive(Deserialize)]
+pub struct HudLayout {
+    //Tilesheet with the icons, relative to the layout file
+    sheet: String,
+    //Screen size the layout is designed for, it is scaled uniformly to fit the render resolution
+    reference_size: (f32, f32),
+    bar_height: f32,
+    background: Color,
+    elements: Vec<HudElement>,
+}
+
+///Status bar at the bottom of the screen
+pub struct Hud {
+    layout: HudLayout,
+    sheet: ImageSheet,
+
+    //Pixels per layout unit
+    scale: f32,
+    //Top left corner of the layout in pixels
+    origin: (f32, f32),
+    bar_height: u32,
+    width: u32,
+}
+
+impl Hud {
+    pub fn load() -> Result<Hud, Box<dyn std::error::Error>> {
+        let file_content = fs::read_to_string(RES_HUD.to_owned() + "layout.ron")?;
+        let layout: HudLayout = ron::from_str(&file_content)?;
+
+        let sheet = ImageSheet::load(&OsString::from(RES_HUD.to_owned() + &layout.sheet))?;
+
+        Ok(Hud {
+            layout,
+            sheet,
+            scale: 1.0,
+            origin: (0.0, 0.0),
+            bar_height: 0,
+            width: 0,
+        })
+    }
+
+    ///Fits the layout to the render resolution, returns the height of the status bar in pixels
+    pub fn resize(&mut self, resolution: (u32, u32)) -> u32 {
+        let (reference_width, reference_height) = self.layout.reference_size;
+
+        self.scale =
+            (resolution.0 as f32 / reference_width).min(resolution.1 as f32 / reference_height);
+        self.bar_height = (self.layout.bar_height * self.scale).round() as u32;
+        self.width = resolution.0;
+
+        //Centered horizontally if the aspect ratio differs from the reference
+        self.origin = (
+            (resolution.0 as f32 - reference_width * self.scale) / 2.0,
+            (resolution.1 - self.bar_height) as f32,
+        );
+
+        self.bar_height
+    }
+
+    fn to_screen(&self, position: (f32, f32)) -> (f32, f32) {
+        (
+            self.origin.0 + position.0 * self.scale,
+            self.origin.1 + position.1 * self.scale,
+        )
+    }
+
+    fn to_screen_size(&self, size: (f32, f32)) -> (f32, f32) {
+        (size.0 * self.scale, size.1 * self.scale)
+    }
+
+    fn value(value: HudValue, player: &Player) -> u32 {
+        match value {
+            HudValue::Health => player.health(),
+            HudValue::Armor => player.inventory().armor(),
+            HudValue::Ammo => player.ammo(),
+            HudValue::Score => player.score(),
+        }
+    }
+
+    fn face_tile(first_tile: u32, count: u32, health: u32) -> u32 {
+        if count <= 1 {
+            return first_tile;
+        }
+
+        //The last tile is only shown when dead, the others split the health range evenly
+        if health == 0 {
+            return first_tile + count - 1;
+        }
+
+        let missing = inventory::MAX_HEALTH.saturating_sub(health);
+        let step = (missing * (count - 1) / inventory::MAX_HEALTH).min(count - 2);
+
+        first_tile + step
+    }
+
+    ///Screen position and scale of text placed at `position` in layout units
+    fn place_text(
+        &self,
+        font: &Font,
+        text: &str,
+        position: (f32, f32),
+        scale: f32,
+        align: Align,
+    ) -> ((f32, f32), f32) {
+        let scale = scale * self.scale;
+        let (x, y) = self.to_screen(position);
+        let width = font.measure(text, scale).0 as f32;
+
+        let x = match align {
+            Align::Left => x,
+            Align::Center => x - width / 2.0,
+            Align::Right => x - width,
+        };
+
+        ((x, y), scale)
+    }
+
+    pub fn draw(&self, overlay: &mut Overlay, font: &Font, player: &Player, level_name: &str) {
+        if self.bar_height == 0 {
+            return;
+        }
+
+        overlay.fill(
+            (0.0, self.origin.1),
+            (self.width as f32, self.bar_height as f32),
+            self.layout.background,
+        );
+
+        for element in &self.layout.elements {
+            match element {
+                HudElement::Panel {
+                    position,
+                    size,
+                    color,
+                } => overlay.fill(
+                    self.to_screen(*position),
+                    self.to_screen_size(*size),
+                    *color,
+                ),
+                HudElement::Icon {
+                    tile,
+                    position,
+                    size,
+                } => overlay.draw_tile(
+                    &self.sheet,
+                    *tile,
+                    self.to_screen(*position),
+                    self.to_screen_size(*size),
+                    [1.0, 1.0, 1.0, 1.0],
+                ),
+                HudElement::Counter {
+                    value,
+                    position,
+                    scale,
+                    color,
+                    align,
+                } => {
+                    let text = Self::value(*value, player).to_string();
+                    let (position, scale) = self.place_text(font, &text, *position, *scale, *align);
+                    overlay.draw_text(font, &text, position, *color, scale);
+                }
+                HudElement::Face {
+                    first_tile,
+                    count,
+                    position,
+                    size,
+                } => overlay.draw_tile(
+                    &self.sheet,
+                    Self::face_tile(*first_tile, *count, player.health()),
+                    self.to_screen(*position),
+                    self.to_screen_size(*size),
+                    [1.0, 1.0, 1.0, 1.0],
+                ),
+                HudElement::LevelName {
+                    position,
+                    scale,
+                    color,
+                    align,
+                } => {
+                    let (position, scale) =
+                        self.place_text(font, level_name, *position, *scale, *align);
+                    overlay.draw_text(font, level_name, position, *color, scale);
+                }
+                HudElement::Key {
+                    key,
+                    tile,
+                    position,
+                    size,
+                } => {
+                    if player.inventory().has_key(*key) {
+                        overlay.draw_tile(
+                            &self.sheet,
+                            *tile,
+                            self.to_screen(*position),
+                            self.to_screen_size(*size),
+                            [1.0, 1.0, 1.0, 1.0],
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use std::ffi::{c_void, OsString};
+
+use super::overlay::ATLAS_BINDING;
+use crate::engine::sprites::SpriteAsset;
+use crate::gpu::TextureSampler;
+
+///A grid of equally sized images kept as one texture, used for overlay graphics
+pub struct ImageSheet {
+    tile_width: u32,
+    tile_height: u32,
+    columns: u32,
+    rows: u32,
+    height: u32,
+    texture: TextureSampler,
+}
+
+impl ImageSheet {
+    pub fn load(path: &OsString) -> Result<ImageSheet, Box<dyn std::error::Error>> {
+        let asset = SpriteAsset::load(path)?;
+
+        //Flipped so that the rows match the framebuffer, which starts at the bottom
+        let img = image::imageops::flip_vertical(&image::open(&asset.file)?.to_rgba8());
+        let (width, height) = img.dimensions();
+        let pixels = img.into_raw();
+
+        let texture = TextureSampler::from(
+            ATLAS_BINDING,
+            width as i32,
+            height as i32,
+            1,
+            pixels.as_ptr() as *const c_void,
+        );
+
+        Ok(ImageSheet {
+            tile_width: width / asset.columns,
+            tile_height: height / asset.rows,
+            columns: asset.columns,
+            rows: asset.rows,
+            height,
+            texture,
+        })
+    }
+
+    ///Bottom left corner of the tile in the flipped texture, tiles are counted row by row from the top left
+    pub fn tile_origin(&self, index: u32) -> (i32, i32) {
+        let index = index.min(self.columns * self.rows - 1);
+        let column = index % self.columns;
+        let row = index / self.columns;
+
+        (
+            (column * self.tile_width) as i32,
+            (self.height - (row + 1) * self.tile_height) as i32,
+        )
+    }
+
+    pub fn tile_size(&self) -> (u32, u32) {
+        (self.tile_width, self.tile_height)
+    }
+
+    pub fn texture(&self) -> &TextureSampler {
+        &self.texture
+    }
+}
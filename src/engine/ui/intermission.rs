@@ -0,0 +1,120 @@
+use super::font::Font;
+use super::overlay::{Color, Overlay};
+use crate::engine::game::LevelStats;
+use crate::engine::I18n;
+
+const BACKGROUND_COLOR: Color = [0.0, 0.1, 0.15, 1.0];
+const HEADING_COLOR: Color = [1.0, 1.0, 1.0, 1.0];
+const TEXT_COLOR: Color = [0.75, 0.75, 0.75, 1.0];
+const HINT_COLOR: Color = [1.0, 0.85, 0.2, 1.0];
+
+///Summary shown after the exit of a level was used
+pub struct Intermission {
+    stats: LevelStats,
+    //Seconds, only campaign maps have one
+    par_time: Option<u32>,
+    //Localisation key of the episode name
+    episode: Option<String>,
+    episode_finished: bool,
+}
+
+impl Intermission {
+    pub fn new(
+        stats: LevelStats,
+        par_time: Option<u32>,
+        episode: Option<String>,
+        episode_finished: bool,
+    ) -> Intermission {
+        Intermission {
+            stats,
+            par_time,
+            episode,
+            episode_finished,
+        }
+    }
+
+    ///Levels without anything to find count as fully explored
+    fn ratio(count: u32, total: u32) -> u32 {
+        (count * 100).checked_div(total).unwrap_or(100)
+    }
+
+    fn format_time(seconds: u32) -> String {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+
+    pub fn draw(&self, overlay: &mut Overlay, font: &Font, i18n: &I18n, level_name: &str) {
+        let (width, height) = overlay.resolution();
+        let (width, height) = (width as f32, height as f32);
+        let scale = (height / 300.0).max(1.0);
+        let line_height = font.line_height() as f32 * scale * 1.5;
+
+        overlay.fill((0.0, 0.0), (width, height), BACKGROUND_COLOR);
+
+        let mut centered = |text: &str, top: f32, color: Color, scale: f32| {
+            let left = (width - font.measure(text, scale).0 as f32) / 2.0;
+            overlay.draw_text(font, text, (left, top), color, scale);
+        };
+
+        if let Some(episode) = &self.episode {
+            centered(
+                i18n.get_translation(episode),
+                height * 0.08,
+                TEXT_COLOR,
+                scale,
+            );
+        }
+
+        let heading = i18n.format("level_complete", &[("map", &level_name)]);
+        centered(&heading, height * 0.18, HEADING_COLOR, scale * 2.0);
+
+        let stats = &self.stats;
+        let mut lines = vec![
+            i18n.plural(
+                "kill_ratio",
+                stats.kills as u64,
+                &[("percent", &Self::ratio(stats.kills, stats.total_kills))],
+            ),
+            i18n.format(
+                "secret_ratio",
+                &[("percent", &Self::ratio(stats.secrets, stats.total_secrets))],
+            ),
+            i18n.format(
+                "item_ratio",
+                &[("percent", &Self::ratio(stats.items, stats.total_items))],
+            ),
+            i18n.format(
+                "level_time",
+                &[("time", &Self::format_time(stats.time as u32))],
+            ),
+        ];
+
+        if let Some(par_time) = self.par_time {
+            lines.push(i18n.format("par_time", &[("time", &Self::format_time(par_time))]));
+        }
+
+        for (i, line) in lines.iter().enumerate() {
+            centered(
+                line,
+                height * 0.38 + i as f32 * line_height,
+                TEXT_COLOR,
+                scale,
+            );
+        }
+
+        if self.episode_finished {
+            let episode = self
+                .episode
+                .as_deref()
+                .map_or("", |episode| i18n.get_translation(episode));
+            let text = i18n.format("episode_complete", &[("episode", &episode)]);
+            centered(&text, height * 0.75, HEADING_COLOR, scale);
+        }
+
+        centered(
+            i18n.get_translation("press_to_continue"),
+            height * 0.88,
+            HINT_COLOR,
+            scale,
+        );
+    }
+}
@@ -0,0 +1,473 @@
+use super::font::Font;
+use super::menuinput::MenuInput;
+use super::overlay::{Color, Overlay};
+use crate::engine::savegame::{QUICKSAVE_SLOT, SLOT_COUNT};
+use crate::engine::{I18n, Settings};
+
+//Offered in addition to the configured resolution
+const RESOLUTIONS: [(u32, u32); 9] = [
+    (640, 480),
+    (800, 600),
+    (1024, 768),
+    (1280, 720),
+    (1366, 768),
+    (1600, 900),
+    (1920, 1080),
+    (2560, 1440),
+    (3840, 2160),
+];
+const FOV_STEP: f32 = 5.0;
+const SENSITIVITY_STEP: f32 = 0.1;
+const VOLUME_STEP: f32 = 0.1;
+
+const TEXT_COLOR: Color = [0.75, 0.75, 0.75, 1.0];
+const SELECTED_COLOR: Color = [1.0, 0.85, 0.2, 1.0];
+const HEADING_COLOR: Color = [1.0, 1.0, 1.0, 1.0];
+//Behind the pause menu the game stays visible
+const OVERLAY_COLOR: Color = [0.0, 0.0, 0.0, 0.7];
+const TITLE_COLOR: Color = [0.0, 0.0, 0.15, 1.0];
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Screen {
+    Title,
+    MapSelect,
+    Pause,
+    Settings,
+    Save,
+    Load,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Setting {
+    Resolution,
+    Language,
+    Fov,
+    MouseSensitivity,
+    MasterVolume,
+    MusicVolume,
+    EffectsVolume,
+}
+
+///What the game has to do in response to the menu
+pub enum MenuAction {
+    Play(String),
+    Resume,
+    Save(u32),
+    Load(u32),
+    QuitToTitle,
+    Quit,
+    //The setting was already written to `Settings`, but has to be applied
+    Changed(Setting),
+}
+
+enum Item {
+    Open(Screen),
+    Play(String),
+    Resume,
+    SaveSlot(u32),
+    LoadSlot(u32),
+    QuitToTitle,
+    Quit,
+    Back,
+    Setting(Setting),
+}
+
+pub struct Menu {
+    //Open screens with their selected item, the last one is shown
+    stack: Vec<(Screen, usize)>,
+    maps: Vec<String>,
+    //Language codes with the name each language gives itself
+    languages: Vec<(String, String)>,
+    //Map of the save game in each slot
+    saves: Vec<Option<String>>,
+    resolution: (u32, u32),
+}
+
+impl Menu {
+    ///`maps` are offered in the map selection in the given order
+    pub fn new(resolution: (u32, u32), maps: Vec<String>) -> Menu {
+        let languages = I18n::available_languages()
+            .into_iter()
+            .map(|lang| {
+                let name = match I18n::from(&lang) {
+                    Ok(i18n) => i18n.get_translation("language_name").to_owned(),
+                    Err(_) => lang.clone(),
+                };
+                (lang, name)
+            })
+            .collect();
+
+        Menu {
+            stack: Vec::new(),
+            maps,
+            languages,
+            saves: Vec::new(),
+            resolution,
+        }
+    }
+
+    ///Has to be called whenever a save game was written
+    pub fn set_saves(&mut self, saves: Vec<Option<String>>) {
+        self.saves = saves;
+    }
+
+    fn save_of(&self, slot: u32) -> Option<&String> {
+        self.saves.get(slot as usize)?.as_ref()
+    }
+
+    pub fn resize(&mut self, resolution: (u32, u32)) {
+        self.resolution = resolution;
+    }
+
+    ///Replaces all open screens
+    pub fn open(&mut self, screen: Screen) {
+        self.stack = vec![(screen, 0)];
+    }
+
+    pub fn is_open(&self) -> bool {
+        !self.stack.is_empty()
+    }
+
+    fn items(&self, screen: Screen) -> Vec<Item> {
+        match screen {
+            Screen::Title => vec![
+                Item::Open(Screen::MapSelect),
+                Item::Open(Screen::Load),
+                Item::Open(Screen::Settings),
+                Item::Quit,
+            ],
+            Screen::MapSelect => self
+                .maps
+                .iter()
+                .map(|map| Item::Play(map.clone()))
+                .chain(std::iter::once(Item::Back))
+                .collect(),
+            Screen::Pause => vec![
+                Item::Resume,
+                Item::Open(Screen::Save),
+                Item::Open(Screen::Load),
+                Item::Open(Screen::Settings),
+                Item::QuitToTitle,
+                Item::Quit,
+            ],
+            Screen::Settings => vec![
+                Item::Setting(Setting::Resolution),
+                Item::Setting(Setting::Language),
+                Item::Setting(Setting::Fov),
+                Item::Setting(Setting::MouseSensitivity),
+                Item::Setting(Setting::MasterVolume),
+                Item::Setting(Setting::MusicVolume),
+                Item::Setting(Setting::EffectsVolume),
+                Item::Back,
+            ],
+            //The quicksave slot is only written by quicksave
+            Screen::Save => (QUICKSAVE_SLOT + 1..SLOT_COUNT)
+                .map(Item::SaveSlot)
+                .chain(std::iter::once(Item::Back))
+                .collect(),
+            Screen::Load => (0..SLOT_COUNT)
+                .map(Item::LoadSlot)
+                .chain(std::iter::once(Item::Back))
+                .collect(),
+        }
+    }
+
+    pub fn handle(&mut self, input: MenuInput, settings: &mut Settings) -> Option<MenuAction> {
+        let (screen, selected) = *self.stack.last()?;
+        let items = self.items(screen);
+        let count = items.len();
+
+        match input {
+            MenuInput::Up => self.select((selected + count - 1) % count),
+            MenuInput::Down => self.select((selected + 1) % count),
+            MenuInput::Left | MenuInput::Right => {
+                let step = if input == MenuInput::Left { -1 } else { 1 };
+
+                if let Item::Setting(setting) = items[selected] {
+                    self.adjust(setting, step, settings);
+                    return Some(MenuAction::Changed(setting));
+                }
+            }
+            MenuInput::Select => return self.activate(&items[selected], settings),
+            MenuInput::Back => return self.back(settings),
+        }
+
+        None
+    }
+
+    fn select(&mut self, index: usize) {
+        if let Some(top) = self.stack.last_mut() {
+            top.1 = index;
+        }
+    }
+
+    fn activate(&mut self, item: &Item, settings: &mut Settings) -> Option<MenuAction> {
+        match item {
+            Item::Open(screen) => {
+                self.stack.push((*screen, 0));
+                None
+            }
+            Item::Play(map) => {
+                self.stack.clear();
+                Some(MenuAction::Play(map.clone()))
+            }
+            Item::Resume => {
+                self.stack.clear();
+                Some(MenuAction::Resume)
+            }
+            Item::SaveSlot(slot) => {
+                self.stack.clear();
+                Some(MenuAction::Save(*slot))
+            }
+            //Empty slots can't be loaded
+            Item::LoadSlot(slot) => {
+                self.save_of(*slot)?;
+                self.stack.clear();
+                Some(MenuAction::Load(*slot))
+            }
+            Item::QuitToTitle => {
+                self.open(Screen::Title);
+                Some(MenuAction::QuitToTitle)
+            }
+            Item::Quit => Some(MenuAction::Quit),
+            Item::Back => self.back(settings),
+            Item::Setting(setting) => {
+                self.adjust(*setting, 1, settings);
+                Some(MenuAction::Changed(*setting))
+            }
+        }
+    }
+
+    fn back(&mut self, settings: &mut Settings) -> Option<MenuAction> {
+        let (screen, _) = self.stack.pop()?;
+
+        if screen == Screen::Settings && settings.modified() {
+            if let Err(e) = settings.save() {
+                eprintln!("Couldn't save settings: {}", e);
+            }
+        }
+
+        match screen {
+            _ if self.is_open() => None,
+            Screen::Pause => Some(MenuAction::Resume),
+            //There is nothing behind the title screen
+            _ => {
+                self.open(Screen::Title);
+                None
+            }
+        }
+    }
+
+    ///Steps the setting to its previous or next value
+    fn adjust(&self, setting: Setting, step: i32, settings: &mut Settings) {
+        match setting {
+            Setting::Resolution => {
+                let mut options = RESOLUTIONS.to_vec();
+                if !options.contains(settings.resolution()) {
+                    options.push(*settings.resolution());
+                    options.sort_unstable();
+                }
+
+                let current = options.iter().position(|r| r == settings.resolution());
+                let next = Self::cycle(current.unwrap_or(0), step, options.len());
+                settings.set_resolution(options[next]);
+            }
+            Setting::Language => {
+                let current = self
+                    .languages
+                    .iter()
+                    .position(|(lang, _)| lang == settings.language());
+                let next = Self::cycle(current.unwrap_or(0), step, self.languages.len());
+
+                if let Some((lang, _)) = self.languages.get(next) {
+                    settings.set_language(lang);
+                }
+            }
+            Setting::Fov => settings.set_fov(settings.fov() + step as f32 * FOV_STEP),
+            Setting::MouseSensitivity => {
+                let sensitivity = settings.mouse_sensitivity() + step as f32 * SENSITIVITY_STEP;
+                //Avoids values like 0.70000005 in the settings file
+                settings.set_mouse_sensitivity((sensitivity * 100.0).round() / 100.0);
+            }
+            Setting::MasterVolume => {
+                settings.set_master_volume(Self::step_volume(settings.master_volume(), step))
+            }
+            Setting::MusicVolume => {
+                settings.set_music_volume(Self::step_volume(settings.music_volume(), step))
+            }
+            Setting::EffectsVolume => {
+                settings.set_effects_volume(Self::step_volume(settings.effects_volume(), step))
+            }
+        }
+    }
+
+    fn step_volume(volume: f32, step: i32) -> f32 {
+        ((volume + step as f32 * VOLUME_STEP) * 10.0).round() / 10.0
+    }
+
+    fn cycle(index: usize, step: i32, len: usize) -> usize {
+        if len == 0 {
+            return 0;
+        }
+        (index as i32 + step).rem_euclid(len as i32) as usize
+    }
+
+    fn text_scale(&self) -> f32 {
+        (self.resolution.1 as f32 / 300.0).max(1.0)
+    }
+
+    ///Top edge and height of every item row in pixels
+    fn rows(&self, count: usize, font: &Font) -> Vec<(f32, f32)> {
+        let height = font.line_height() as f32 * self.text_scale() * 1.5;
+        let top = self.resolution.1 as f32 * 0.4;
+
+        (0..count)
+            .map(|i| (top + i as f32 * height, height))
+            .collect()
+    }
+
+    ///Selects the item under the cursor, `position` is in pixels of the rendered image
+    pub fn hover(&mut self, position: (f32, f32), font: &Font) {
+        if let Some(index) = self.item_at(position, font) {
+            self.select(index);
+        }
+    }
+
+    pub fn click(
+        &mut self,
+        position: (f32, f32),
+        font: &Font,
+        settings: &mut Settings,
+    ) -> Option<MenuAction> {
+        let index = self.item_at(position, font)?;
+        self.select(index);
+        self.handle(MenuInput::Select, settings)
+    }
+
+    fn item_at(&self, position: (f32, f32), font: &Font) -> Option<usize> {
+        let (screen, _) = *self.stack.last()?;
+
+        self.rows(self.items(screen).len(), font)
+            .iter()
+            .position(|(top, height)| position.1 >= *top && position.1 < top + height)
+    }
+
+    fn heading(screen: Screen) -> &'static str {
+        match screen {
+            Screen::Title => "game_title",
+            Screen::MapSelect => "select_map",
+            Screen::Pause => "paused",
+            Screen::Settings => "settings",
+            Screen::Save => "save_game",
+            Screen::Load => "load_game",
+        }
+    }
+
+    fn setting_value(&self, setting: Setting, settings: &Settings) -> String {
+        match setting {
+            Setting::Resolution => {
+                let (width, height) = settings.resolution();
+                format!("{}x{}", width, height)
+            }
+            Setting::Language => self
+                .languages
+                .iter()
+                .find(|(lang, _)| lang == settings.language())
+                .map_or(settings.language().clone(), |(_, name)| name.clone()),
+            Setting::Fov => format!("{:.0}°", settings.fov()),
+            Setting::MouseSensitivity => format!("{:.2}", settings.mouse_sensitivity()),
+            Setting::MasterVolume => format!("{:.0}%", settings.master_volume() * 100.0),
+            Setting::MusicVolume => format!("{:.0}%", settings.music_volume() * 100.0),
+            Setting::EffectsVolume => format!("{:.0}%", settings.effects_volume() * 100.0),
+        }
+    }
+
+    fn label(&self, item: &Item, i18n: &I18n, settings: &Settings) -> String {
+        let key = match item {
+            Item::Open(Screen::MapSelect) => "play",
+            Item::Open(screen) => Self::heading(*screen),
+            Item::Play(map) => map,
+            Item::Resume => "resume",
+            Item::SaveSlot(slot) | Item::LoadSlot(slot) => {
+                let map = match self.save_of(*slot) {
+                    Some(map) => i18n.get_translation(map),
+                    None => i18n.get_translation("empty_slot"),
+                };
+
+                return if *slot == QUICKSAVE_SLOT {
+                    i18n.format("quicksave_slot", &[("map", &map)])
+                } else {
+                    i18n.format("save_slot", &[("slot", slot), ("map", &map)])
+                };
+            }
+            Item::QuitToTitle => "main_menu",
+            Item::Quit => "quit",
+            Item::Back => "back",
+            Item::Setting(setting) => {
+                let name = match setting {
+                    Setting::Resolution => "resolution",
+                    Setting::Language => "language",
+                    Setting::Fov => "field_of_view",
+                    Setting::MouseSensitivity => "mouse_sensitivity",
+                    Setting::MasterVolume => "master_volume",
+                    Setting::MusicVolume => "music_volume",
+                    Setting::EffectsVolume => "effects_volume",
+                };
+
+                return i18n.format(
+                    "setting_value",
+                    &[
+                        ("setting", &i18n.get_translation(name)),
+                        ("value", &self.setting_value(*setting, settings)),
+                    ],
+                );
+            }
+        };
+
+        i18n.get_translation(key).to_owned()
+    }
+
+    pub fn draw(&self, overlay: &mut Overlay, font: &Font, i18n: &I18n, settings: &Settings) {
+        let (screen, selected) = match self.stack.last() {
+            Some(top) => *top,
+            None => return,
+        };
+
+        let (width, height) = (self.resolution.0 as f32, self.resolution.1 as f32);
+        let background = if self.stack[0].0 == Screen::Title {
+            TITLE_COLOR
+        } else {
+            OVERLAY_COLOR
+        };
+        overlay.fill((0.0, 0.0), (width, height), background);
+
+        let scale = self.text_scale();
+        let centered = |text: &str, scale: f32| (width - font.measure(text, scale).0 as f32) / 2.0;
+
+        let heading = i18n.get_translation(Self::heading(screen));
+        let heading_scale = scale * 2.0;
+        overlay.draw_text(
+            font,
+            heading,
+            (centered(heading, heading_scale), height * 0.15),
+            HEADING_COLOR,
+            heading_scale,
+        );
+
+        let items = self.items(screen);
+        for (i, (item, (top, _))) in items.iter().zip(self.rows(items.len(), font)).enumerate() {
+            let mut label = self.label(item, i18n, settings);
+
+            let color = if i == selected {
+                if let Item::Setting(_) = item {
+                    label = format!("< {} >", label);
+                }
+                SELECTED_COLOR
+            } else {
+                TEXT_COLOR
+            };
+
+            overlay.draw_text(font, &label, (centered(&label, scale), top), color, scale);
+        }
+    }
+}
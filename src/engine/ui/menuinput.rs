@@ -0,0 +1,85 @@
+use glfw::{Action, GamepadAxis, GamepadButton, JoystickId, Key};
+
+//How far a stick has to be pushed to count as a direction
+const STICK_THRESHOLD: f32 = 0.5;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MenuInput {
+    Up,
+    Down,
+    Left,
+    Right,
+    Select,
+    Back,
+}
+
+const INPUTS: [MenuInput; 6] = [
+    MenuInput::Up,
+    MenuInput::Down,
+    MenuInput::Left,
+    MenuInput::Right,
+    MenuInput::Select,
+    MenuInput::Back,
+];
+
+impl MenuInput {
+    pub fn from_key(key: Key) -> Option<MenuInput> {
+        match key {
+            Key::Up | Key::W => Some(MenuInput::Up),
+            Key::Down | Key::S => Some(MenuInput::Down),
+            Key::Left | Key::A => Some(MenuInput::Left),
+            Key::Right | Key::D => Some(MenuInput::Right),
+            Key::Enter | Key::KpEnter | Key::Space => Some(MenuInput::Select),
+            Key::Escape | Key::Backspace => Some(MenuInput::Back),
+            _ => None,
+        }
+    }
+}
+
+///Turns the first connected gamepad into menu inputs, each press is reported once
+pub struct Gamepad {
+    held: [bool; 6],
+}
+
+impl Gamepad {
+    pub fn new() -> Gamepad {
+        Gamepad { held: [false; 6] }
+    }
+
+    pub fn poll(&mut self, glfw: &glfw::Glfw) -> Vec<MenuInput> {
+        let joystick = glfw.get_joystick(JoystickId::Joystick1);
+
+        let state = match joystick.get_gamepad_state() {
+            Some(state) if joystick.is_gamepad() => state,
+            _ => {
+                self.held = [false; 6];
+                return Vec::new();
+            }
+        };
+
+        let button = |button| state.get_button_state(button) == Action::Press;
+        let stick_x = state.get_axis(GamepadAxis::AxisLeftX);
+        let stick_y = state.get_axis(GamepadAxis::AxisLeftY);
+
+        //Same order as INPUTS
+        let pressed = [
+            button(GamepadButton::ButtonDpadUp) || stick_y < -STICK_THRESHOLD,
+            button(GamepadButton::ButtonDpadDown) || stick_y > STICK_THRESHOLD,
+            button(GamepadButton::ButtonDpadLeft) || stick_x < -STICK_THRESHOLD,
+            button(GamepadButton::ButtonDpadRight) || stick_x > STICK_THRESHOLD,
+            button(GamepadButton::ButtonA),
+            button(GamepadButton::ButtonB) || button(GamepadButton::ButtonStart),
+        ];
+
+        let inputs = INPUTS
+            .iter()
+            .zip(pressed.iter().zip(self.held.iter()))
+            .filter(|(_, (pressed, held))| **pressed && !**held)
+            .map(|(input, _)| *input)
+            .collect();
+
+        self.held = pressed;
+
+        inputs
+    }
+}
@@ -0,0 +1,17 @@
+mod font;
+mod fontasset;
+mod hud;
+mod imagesheet;
+mod intermission;
+mod menu;
+mod menuinput;
+mod overlay;
+mod weaponview;
+
+pub use font::Font;
+pub use hud::Hud;
+pub use intermission::Intermission;
+pub use menu::{Menu, MenuAction, Screen, Setting};
+pub use menuinput::{Gamepad, MenuInput};
+pub use overlay::Overlay;
+pub use weaponview::WeaponView;
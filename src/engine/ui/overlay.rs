@@ -0,0 +1,254 @@
+use super::font::Font;
+use super::imagesheet::ImageSheet;
+use crate::gpu::{Shader, TextureSampler, SSBO};
+
+//Texture unit of the sheet the current batch samples from
+pub const ATLAS_BINDING: u32 = 11;
+const QUAD_BINDING: u32 = 11;
+
+pub type Color = [f32; 4];
+
+///Has to match Overlay_Quad in ui/overlay.glsl
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct OverlayQuad {
+    //Bottom left corner in the framebuffer
+    screen_x: i32,
+    screen_y: i32,
+    //Bottom left corner in the flipped atlas, negative for untextured quads
+    atlas_x: i32,
+    atlas_y: i32,
+    //Size in atlas pixels
+    width: i32,
+    height: i32,
+    scale_x: f32,
+    scale_y: f32,
+    color: Color,
+}
+
+const _: () = assert!(std::mem::size_of::<OverlayQuad>() == 48);
+const _: () = assert!(std::mem::offset_of!(OverlayQuad, color) == 32);
+
+//Consecutive quads sampling from the same texture, 0 for untextured ones
+struct Batch {
+    texture: u32,
+    first_quad: usize,
+}
+
+///Collects text and images during a frame and draws them on top of the rendered image.
+///Positions are in pixels from the top left of the image
+pub struct Overlay {
+    resolution: (u32, u32),
+    quads: Vec<OverlayQuad>,
+    batches: Vec<Batch>,
+    ssbo: SSBO,
+    gpu_capacity: isize,
+    shader: Shader,
+}
+
+impl Overlay {
+    pub fn load(resolution: (u32, u32)) -> Result<Overlay, Box<dyn std::error::Error>> {
+        let shader = Shader::from("./src/shader/ui/overlay.glsl", gl::COMPUTE_SHADER)?;
+
+        Ok(Overlay {
+            resolution,
+            quads: Vec::new(),
+            batches: Vec::new(),
+            ssbo: SSBO::empty(QUAD_BINDING, 0, gl::DYNAMIC_DRAW),
+            gpu_capacity: 0,
+            shader,
+        })
+    }
+
+    pub fn resize(&mut self, resolution: (u32, u32)) {
+        self.resolution = resolution;
+    }
+
+    pub fn resolution(&self) -> (u32, u32) {
+        self.resolution
+    }
+
+    fn push(&mut self, texture: Option<&TextureSampler>, quad: OverlayQuad) {
+        let texture = texture.map_or(0, |t| t.id);
+
+        //Untextured quads get batches of their own too, so the quads stay in the order they were pushed
+        if self
+            .batches
+            .last()
+            .is_none_or(|batch| batch.texture != texture)
+        {
+            self.batches.push(Batch {
+                texture,
+                first_quad: self.quads.len(),
+            });
+        }
+
+        self.quads.push(quad);
+    }
+
+    ///Converts the top edge of something `height` pixels high to the bottom row in the framebuffer
+    fn to_screen_y(&self, top: f32, height: f32) -> i32 {
+        self.resolution.1 as i32 - (top + height).round() as i32
+    }
+
+    pub fn fill(&mut self, position: (f32, f32), size: (f32, f32), color: Color) {
+        let quad = OverlayQuad {
+            screen_x: position.0.round() as i32,
+            screen_y: self.to_screen_y(position.1, size.1),
+            atlas_x: -1,
+            atlas_y: -1,
+            width: size.0.round() as i32,
+            height: size.1.round() as i32,
+            scale_x: 1.0,
+            scale_y: 1.0,
+            color,
+        };
+
+        self.push(None, quad);
+    }
+
+    ///Draws a tile of the sheet stretched to `size`, its colors are multiplied with `color`
+    pub fn draw_tile(
+        &mut self,
+        sheet: &ImageSheet,
+        tile: u32,
+        position: (f32, f32),
+        size: (f32, f32),
+        color: Color,
+    ) {
+        self.draw_tile_above(sheet, tile, position, size, f32::INFINITY, color);
+    }
+
+    ///Like `draw_tile`, but rows of the tile below the pixel row `bottom` are left out,
+    ///e.g. to keep the weapon out of the status bar
+    pub fn draw_tile_above(
+        &mut self,
+        sheet: &ImageSheet,
+        tile: u32,
+        position: (f32, f32),
+        size: (f32, f32),
+        bottom: f32,
+        color: Color,
+    ) {
+        let (atlas_x, atlas_y) = sheet.tile_origin(tile);
+        let (tile_width, tile_height) = sheet.tile_size();
+
+        let scale_y = size.1 / tile_height as f32;
+        let visible = ((bottom - position.1) / scale_y)
+            .floor()
+            .clamp(0.0, tile_height as f32) as u32;
+        if visible == 0 {
+            return;
+        }
+
+        //The atlas is flipped, the hidden rows are the first ones of the tile
+        let quad = OverlayQuad {
+            screen_x: position.0.round() as i32,
+            screen_y: self.to_screen_y(position.1, visible as f32 * scale_y),
+            atlas_x,
+            atlas_y: atlas_y + (tile_height - visible) as i32,
+            width: tile_width as i32,
+            height: visible as i32,
+            scale_x: size.0 / tile_width as f32,
+            scale_y,
+            color,
+        };
+
+        self.push(Some(sheet.texture()), quad);
+    }
+
+    ///Draws text with its first line starting at `position`, lines are separated by '\n'
+    pub fn draw_text(
+        &mut self,
+        font: &Font,
+        text: &str,
+        position: (f32, f32),
+        color: Color,
+        scale: f32,
+    ) {
+        let line_height = font.line_height() as f32 * scale;
+
+        let mut pen_x = position.0;
+        let mut baseline = position.1 + font.ascent() as f32 * scale;
+
+        for c in text.chars() {
+            if c == '\n' {
+                pen_x = position.0;
+                baseline += line_height;
+                continue;
+            }
+
+            let glyph = *font.glyph(c);
+
+            if glyph.width > 0 && glyph.height > 0 {
+                let height = glyph.height as f32 * scale;
+                let top = baseline - glyph.y_offset as f32 * scale - height;
+
+                let quad = OverlayQuad {
+                    screen_x: (pen_x + glyph.x_offset as f32 * scale).round() as i32,
+                    screen_y: self.to_screen_y(top, height),
+                    atlas_x: glyph.x as i32,
+                    atlas_y: (font.atlas_height() - glyph.y - glyph.height) as i32,
+                    width: glyph.width as i32,
+                    height: glyph.height as i32,
+                    scale_x: scale,
+                    scale_y: scale,
+                    color,
+                };
+
+                self.push(Some(font.texture()), quad);
+            }
+
+            pen_x += glyph.advance as f32 * scale;
+        }
+    }
+
+    ///Draws everything queued since the last call into the framebuffer image
+    pub fn render(&mut self) {
+        if self.quads.is_empty() {
+            return;
+        }
+
+        if self.quads.len() as isize > self.gpu_capacity {
+            self.gpu_capacity = self.quads.len().next_power_of_two() as isize;
+            self.ssbo = SSBO::empty(
+                QUAD_BINDING,
+                self.gpu_capacity * std::mem::size_of::<OverlayQuad>() as isize,
+                gl::DYNAMIC_DRAW,
+            );
+        }
+
+        self.ssbo.update(&self.quads, 0);
+
+        for (i, batch) in self.batches.iter().enumerate() {
+            let end = self
+                .batches
+                .get(i + 1)
+                .map_or(self.quads.len(), |next| next.first_quad);
+            let quads = &self.quads[batch.first_quad..end];
+
+            if batch.texture != 0 {
+                unsafe {
+                    gl::ActiveTexture(gl::TEXTURE0 + ATLAS_BINDING);
+                    gl::BindTexture(gl::TEXTURE_2D_ARRAY, batch.texture);
+                }
+            }
+
+            //One dispatch per quad: overlapping quads blend in order and each one only covers its own pixels
+            let scaled = |size: i32, scale: f32| (size as f32 * scale).ceil().max(0.0) as u32;
+            for (index, quad) in quads.iter().enumerate() {
+                self.shader
+                    .set_uint("quad_idx", (batch.first_quad + index) as u32);
+                self.shader.dispatch(
+                    scaled(quad.width, quad.scale_x),
+                    scaled(quad.height, quad.scale_y),
+                    1,
+                    gl::SHADER_IMAGE_ACCESS_BARRIER_BIT,
+                );
+            }
+        }
+
+        self.quads.clear();
+        self.batches.clear();
+    }
+}
@@ -0,0 +1,52 @@
+use std::ffi::OsString;
+
+use super::imagesheet::ImageSheet;
+use super::overlay::Overlay;
+
+const RES_WEAPON_SHEET: &str = "./res/weapons/sheet.ron";
+
+//Height of the weapon as a part of the 3D view height
+const VIEW_FRACTION: f32 = 0.6;
+
+///The weapon in the players hand, drawn at the bottom of the 3D view
+pub struct WeaponView {
+    sheet: ImageSheet,
+    view_resolution: (u32, u32),
+}
+
+impl WeaponView {
+    pub fn load() -> Result<WeaponView, Box<dyn std::error::Error>> {
+        Ok(WeaponView {
+            sheet: ImageSheet::load(&OsString::from(RES_WEAPON_SHEET))?,
+            view_resolution: (0, 0),
+        })
+    }
+
+    ///The status bar takes the rows below the view, the weapon must not cover it
+    pub fn resize(&mut self, view_resolution: (u32, u32)) {
+        self.view_resolution = view_resolution;
+    }
+
+    ///`lowered` moves the weapon down, 1.0 is out of sight
+    pub fn draw(&self, overlay: &mut Overlay, frame: u32, lowered: f32) {
+        let (view_width, view_height) = self.view_resolution;
+        let (tile_width, tile_height) = self.sheet.tile_size();
+
+        let height = view_height as f32 * VIEW_FRACTION;
+        let width = height * tile_width as f32 / tile_height.max(1) as f32;
+
+        let position = (
+            (view_width as f32 - width) / 2.0,
+            view_height as f32 - height + lowered.clamp(0.0, 1.0) * height,
+        );
+
+        overlay.draw_tile_above(
+            &self.sheet,
+            frame,
+            position,
+            (width, height),
+            view_height as f32,
+            [1.0, 1.0, 1.0, 1.0],
+        );
+    }
+}
@@ -1,5 +1,8 @@
 mod math;
+pub mod paths;
+mod random;
 mod vector;
 
 pub use math::Mappable;
+pub use random::Random;
 pub use vector::Vector2;
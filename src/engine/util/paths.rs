@@ -0,0 +1,19 @@
+use std::env;
+use std::path::PathBuf;
+
+const APP_DIR: &str = "rustywolf";
+
+///Per user configuration directory, following the XDG base directory specification
+pub fn config_dir() -> Option<PathBuf> {
+    if let Some(dir) = env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        return Some(PathBuf::from(dir).join(APP_DIR));
+    }
+
+    if cfg!(windows) {
+        if let Some(dir) = env::var_os("APPDATA") {
+            return Some(PathBuf::from(dir).join(APP_DIR));
+        }
+    }
+
+    env::var_os("HOME").map(|home| PathBuf::from(home).join(".config").join(APP_DIR))
+}
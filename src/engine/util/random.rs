@@ -0,0 +1,32 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+///Xorshift generator for gameplay randomness (spread, ...), not suited for anything else
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    ///Seeded from the clock
+    pub fn new() -> Random {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_nanos() as u64);
+
+        //The state must never be zero
+        Random { state: seed | 1 }
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+
+        (self.state >> 32) as u32
+    }
+
+    ///Uniformly distributed in [min, max)
+    pub fn range(&mut self, min: f32, max: f32) -> f32 {
+        let unit = self.next_u32() as f32 / (u32::MAX as f32 + 1.0);
+        min + (max - min) * unit
+    }
+}
@@ -1,8 +1,8 @@
 use std::ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign};
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Deserialize, Clone, Copy)]
+#[derive(Serialize, Deserialize, Clone, Copy)]
 #[repr(C)]
 pub struct Vector2<T> {
     pub x: T,
@@ -31,6 +31,32 @@ impl<T: Add<Output = T> + Sub<Output = T> + Mul<Output = T> + Copy> Vector2<T> {
     }
 }
 
+impl Vector2<f32> {
+    ///Unit vector for an angle in degrees, 0.0 points along +x
+    pub fn from_angle(angle: f32) -> Vector2<f32> {
+        Vector2::new(angle.to_radians().cos(), angle.to_radians().sin())
+    }
+
+    pub fn mag(&self) -> f32 {
+        self.mag_sq().sqrt()
+    }
+
+    ///Rotated counterclockwise by degrees, the same way angles map to directions
+    pub fn rotated(&self, angle: f32) -> Vector2<f32> {
+        let (sin, cos) = angle.to_radians().sin_cos();
+        Vector2::new(self.x * cos - self.y * sin, self.x * sin + self.y * cos)
+    }
+
+    pub fn normalized(&self) -> Vector2<f32> {
+        let mag = self.mag();
+        if mag == 0.0 {
+            return *self;
+        }
+
+        Vector2::new(self.x / mag, self.y / mag)
+    }
+}
+
 impl<T: Add> Add for Vector2<T> {
     type Output = Vector2<T::Output>;
 
@@ -0,0 +1,269 @@
+use serde::{Deserialize, Serialize};
+
+use super::weapontable::{WeaponDefinition, WeaponTable};
+use crate::engine::util::Random;
+use crate::engine::Player;
+
+//Seconds it takes to lower or raise a weapon when switching
+const SWITCH_DURATION: f32 = 0.2;
+
+///A shot fired this tick, traced or launched by the game
+#[derive(Clone)]
+pub struct Shot {
+    pub damage: u32,
+    pub range: f32,
+    //Degrees relative to where the player looks
+    pub angle: f32,
+    pub silent: bool,
+    //Entity launched instead of tracing the shot
+    pub projectile: Option<String>,
+}
+
+///What is kept of the weapons in save games
+#[derive(Serialize, Deserialize)]
+pub struct ArsenalState {
+    pub owned: Vec<String>,
+    pub current: String,
+}
+
+enum Holster {
+    Ready,
+    //Lowering the current weapon to switch to the one at the index
+    Lowering(usize),
+    Raising,
+}
+
+///The players weapons: which ones are owned, the one in hand, firing and switching
+pub struct Arsenal {
+    table: WeaponTable,
+    owned: Vec<bool>,
+    current: usize,
+    holster: Holster,
+    //Seconds since lowering or raising started
+    switch_time: f32,
+    //Seconds until the next shot is possible
+    cooldown: f32,
+    //Seconds since the last shot, drives the fire animation
+    fire_time: Option<f32>,
+    trigger: bool,
+    //Whether the trigger was held for a shot already, only automatic weapons keep firing
+    fired: bool,
+    random: Random,
+}
+
+impl Arsenal {
+    pub fn load() -> Result<Arsenal, Box<dyn std::error::Error>> {
+        let mut arsenal = Arsenal {
+            table: WeaponTable::load()?,
+            owned: Vec::new(),
+            current: 0,
+            holster: Holster::Ready,
+            switch_time: 0.0,
+            cooldown: 0.0,
+            fire_time: None,
+            trigger: false,
+            fired: false,
+            random: Random::new(),
+        };
+        arsenal.reset();
+
+        Ok(arsenal)
+    }
+
+    ///Back to the weapons a game starts with, e.g. after dying
+    pub fn reset(&mut self) {
+        self.owned = self.table.weapons.iter().map(|w| w.start).collect();
+        self.current = self.owned.iter().rposition(|owned| *owned).unwrap_or(0);
+        self.holster = Holster::Ready;
+        self.cooldown = 0.0;
+        self.fire_time = None;
+        self.set_trigger(false);
+    }
+
+    fn weapon(&self) -> &WeaponDefinition {
+        &self.table.weapons[self.current]
+    }
+
+    pub fn set_trigger(&mut self, pulled: bool) {
+        self.trigger = pulled;
+        if !pulled {
+            self.fired = false;
+        }
+    }
+
+    ///Switches to the weapon with the index (number key - 1) if it is owned
+    pub fn select(&mut self, index: usize) {
+        if !self.owned.get(index).copied().unwrap_or(false) {
+            return;
+        }
+
+        match self.holster {
+            Holster::Lowering(_) => self.holster = Holster::Lowering(index),
+            _ if index == self.current => {}
+            Holster::Raising => {
+                //Lowers from where raising got to
+                self.switch_time = SWITCH_DURATION - self.switch_time;
+                self.holster = Holster::Lowering(index);
+            }
+            Holster::Ready => {
+                self.switch_time = 0.0;
+                self.holster = Holster::Lowering(index);
+            }
+        }
+    }
+
+    ///Adds a picked up weapon and switches to it, returns false if it was owned already
+    pub fn give(&mut self, name: &str) -> bool {
+        let index = match self.table.weapons.iter().position(|w| w.name == name) {
+            Some(index) => index,
+            None => {
+                eprintln!("Picked up weapon '{}' doesn't exist", name);
+                return false;
+            }
+        };
+
+        if self.owned[index] {
+            return false;
+        }
+
+        self.owned[index] = true;
+        self.select(index);
+        true
+    }
+
+    fn is_usable(&self, index: usize, player: &Player) -> bool {
+        self.owned[index]
+            && self.table.weapons[index]
+                .ammo
+                .is_none_or(|ammo| player.ammo_of(ammo) > 0)
+    }
+
+    ///Out of ammo, takes the last owned weapon that still works
+    fn select_usable(&mut self, player: &Player) {
+        if let Some(index) = (0..self.owned.len())
+            .rev()
+            .find(|i| self.is_usable(*i, player))
+        {
+            self.select(index);
+        }
+    }
+
+    ///Returns the shot if the weapon fired, ammo is taken from the player
+    pub fn update(&mut self, player: &mut Player, delta_time: f32) -> Option<Shot> {
+        self.cooldown = (self.cooldown - delta_time).max(0.0);
+        if let Some(fire_time) = &mut self.fire_time {
+            *fire_time += delta_time;
+        }
+
+        match self.holster {
+            Holster::Ready => {}
+            Holster::Lowering(next) => {
+                self.switch_time += delta_time;
+                if self.switch_time >= SWITCH_DURATION {
+                    self.current = next;
+                    self.holster = Holster::Raising;
+                    self.switch_time = 0.0;
+                    self.fire_time = None;
+                }
+                return None;
+            }
+            Holster::Raising => {
+                self.switch_time += delta_time;
+                if self.switch_time >= SWITCH_DURATION {
+                    self.holster = Holster::Ready;
+                }
+                return None;
+            }
+        }
+
+        if !self.trigger || self.cooldown > 0.0 || (self.fired && !self.weapon().automatic) {
+            return None;
+        }
+
+        if let Some(ammo) = self.weapon().ammo {
+            if !player.use_ammo(ammo) {
+                self.select_usable(player);
+                return None;
+            }
+        }
+
+        self.fired = true;
+        self.cooldown = self.weapon().cooldown();
+        self.fire_time = Some(0.0);
+
+        let weapon = &self.table.weapons[self.current];
+        Some(Shot {
+            damage: weapon.damage,
+            range: weapon.range,
+            angle: self.random.range(-weapon.spread, weapon.spread),
+            silent: weapon.silent,
+            projectile: weapon.projectile.clone(),
+        })
+    }
+
+    ///Tile of the weapon sheet to show
+    pub fn frame(&self) -> u32 {
+        let weapon = self.weapon();
+        let frames = &weapon.fire_frames;
+
+        let index = self
+            .fire_time
+            .map(|time| (time / weapon.cooldown() * frames.len() as f32) as usize);
+
+        match index {
+            Some(index) if index < frames.len() => frames[index],
+            _ => weapon.idle_frame,
+        }
+    }
+
+    ///0.0 for a weapon in hand, 1.0 for one out of sight
+    pub fn lowered(&self) -> f32 {
+        let progress = (self.switch_time / SWITCH_DURATION).min(1.0);
+
+        match self.holster {
+            Holster::Ready => 0.0,
+            Holster::Lowering(_) => progress,
+            Holster::Raising => 1.0 - progress,
+        }
+    }
+
+    pub fn state(&self) -> ArsenalState {
+        ArsenalState {
+            owned: self
+                .table
+                .weapons
+                .iter()
+                .zip(&self.owned)
+                .filter(|(_, owned)| **owned)
+                .map(|(weapon, _)| weapon.name.clone())
+                .collect(),
+            current: self.weapon().name.clone(),
+        }
+    }
+
+    pub fn restore(&mut self, state: &ArsenalState) {
+        let index = |name: &str| {
+            let index = self.table.weapons.iter().position(|w| w.name == name);
+            if index.is_none() {
+                eprintln!("Weapon '{}' of the save game doesn't exist", name);
+            }
+            index
+        };
+
+        let owned: Vec<usize> = state.owned.iter().filter_map(|name| index(name)).collect();
+        let current = index(&state.current);
+
+        self.owned = (0..self.table.weapons.len())
+            .map(|i| owned.contains(&i))
+            .collect();
+        self.current = current
+            .filter(|current| self.owned[*current])
+            .or_else(|| self.owned.iter().position(|owned| *owned))
+            .unwrap_or(0);
+
+        self.holster = Holster::Ready;
+        self.cooldown = 0.0;
+        self.fire_time = None;
+        self.set_trigger(false);
+    }
+}
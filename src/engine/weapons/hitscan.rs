@@ -0,0 +1,59 @@
+use crate::engine::entities::{EntityId, EntityRegistry};
+use crate::engine::navigation;
+use crate::engine::{Vector2, World};
+
+pub struct Hit {
+    pub entity: EntityId,
+    pub distance: f32,
+}
+
+///Nearest shootable entity a ray from `origin` hits before a wall or the end of its range.
+///Billboards face the shooter, fixed sprites stand across their direction, both as wide as their template
+pub fn trace(
+    world: &World,
+    entities: &EntityRegistry,
+    origin: Vector2<f32>,
+    direction: Vector2<f32>,
+    range: f32,
+) -> Option<Hit> {
+    let direction = direction.normalized();
+    let range = navigation::cast_ray(world, origin, direction, range).unwrap_or(range);
+
+    entities
+        .iter()
+        .filter(|entity| entity.is_alive())
+        .filter_map(|entity| {
+            let sprite = entity.sprite.as_ref()?.borrow();
+            let template = sprite.template();
+            if !template.is_shootable() {
+                return None;
+            }
+
+            let half_width = sprite.width() / 2.0;
+            let to_entity = entity.position - origin;
+
+            let distance = if template.is_billboard() {
+                let along = to_entity.x * direction.x + to_entity.y * direction.y;
+                let across = (to_entity.x * direction.y - to_entity.y * direction.x).abs();
+                (across <= half_width).then_some(along)?
+            } else {
+                //Where the ray crosses the line through the sprite, `side` is measured from its center
+                let facing = entity.direction.normalized();
+                let span = Vector2::new(-facing.y, facing.x);
+                let denominator = direction.x * span.y - direction.y * span.x;
+                if denominator.abs() < f32::EPSILON {
+                    return None;
+                }
+
+                let along = (to_entity.x * span.y - to_entity.y * span.x) / denominator;
+                let side = (to_entity.x * direction.y - to_entity.y * direction.x) / denominator;
+                (side.abs() <= half_width).then_some(along)?
+            };
+
+            (distance > 0.0 && distance <= range).then_some(Hit {
+                entity: entity.id(),
+                distance,
+            })
+        })
+        .min_by(|a, b| a.distance.total_cmp(&b.distance))
+}
@@ -0,0 +1,7 @@
+mod arsenal;
+mod hitscan;
+mod weapontable;
+
+pub use arsenal::{Arsenal, ArsenalState, Shot};
+pub use hitscan::trace;
+pub use weapontable::AmmoType;
@@ -0,0 +1,68 @@
+use serde::Deserialize;
+use std::fs;
+
+const RES_WEAPONS: &str = "./res/weapons/weapons.ron";
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum AmmoType {
+    Bullets,
+    Rockets,
+}
+
+#[derive(Deserialize)]
+pub struct WeaponDefinition {
+    pub name: String,
+    pub damage: u32,
+    //Cells
+    pub range: f32,
+    //Shots per second, the fire animation is played within one shot
+    pub fire_rate: f32,
+    //Melee weapons don't need ammo
+    #[serde(default)]
+    pub ammo: Option<AmmoType>,
+    //Degrees a shot may stray to either side
+    #[serde(default)]
+    pub spread: f32,
+    //Keeps firing while the trigger is held
+    #[serde(default)]
+    pub automatic: bool,
+    //Silent weapons don't alert enemies
+    #[serde(default)]
+    pub silent: bool,
+    //Entity of the spawn table launched instead of a hitscan shot, `damage` and `range` are unused then
+    #[serde(default)]
+    pub projectile: Option<String>,
+    //Owned when a game starts, the others have to be picked up
+    #[serde(default)]
+    pub start: bool,
+
+    //Tiles of the weapon sheet
+    pub idle_frame: u32,
+    pub fire_frames: Vec<u32>,
+}
+
+impl WeaponDefinition {
+    ///Seconds between two shots
+    pub fn cooldown(&self) -> f32 {
+        1.0 / self.fire_rate.max(0.01)
+    }
+}
+
+///All weapons, their position in the list is the number key selecting them
+#[derive(Deserialize)]
+pub struct WeaponTable {
+    pub weapons: Vec<WeaponDefinition>,
+}
+
+impl WeaponTable {
+    pub fn load() -> Result<WeaponTable, Box<dyn std::error::Error>> {
+        let file_content = fs::read_to_string(RES_WEAPONS)?;
+        let table: WeaponTable = ron::from_str(&file_content)?;
+
+        if table.weapons.is_empty() {
+            return Err(format!("{} doesn't define any weapons", RES_WEAPONS).into());
+        }
+
+        Ok(table)
+    }
+}
@@ -1,13 +1,54 @@
 use serde::Deserialize;
 use std::{ffi::OsString, fs};
 
-use crate::gpu::{self, Framebuffer, Shader, SSBO};
+use crate::gpu::{self, Shader, SSBO};
 
 use super::{
-    sprites::{SpriteAsset, SpriteRegistry, Tilesheet},
+    inventory::Key,
+    navigation::{self, Cell, NavGrid},
+    settings::Difficulty,
+    sprites::{SpriteAsset, SpriteOverrides, SpriteRegistry, Tilesheet},
     Player, Vector2,
 };
 
+///Where a player enters the map
+#[derive(Deserialize)]
+pub struct PlayerStart {
+    pub position: Vector2<f32>,
+    //Degrees, 0.0 faces along +x like sprite angles
+    pub angle: f32,
+    //Starts without a difficulty are used on all of them
+    #[serde(default)]
+    pub difficulty: Option<Difficulty>,
+    //Co-op and deathmatch maps have one start per player, single player uses slot 0
+    #[serde(default)]
+    pub slot: u32,
+}
+
+///Entity placed by the map: name from the spawn table, position, angle and optionally
+///changes to its sprite, e.g. `("barrel", Vector2(x: 1.5, y: 1.5), 0.0, (scale: Some((0.4, 0.5))))`
+#[derive(Deserialize, Clone)]
+pub struct Spawn(
+    pub String,
+    pub Vector2<f32>,
+    pub f32,
+    #[serde(default)] pub SpriteOverrides,
+);
+
+///Turn arrow on the floor, patrolling actors entering the cell continue along its heading
+#[derive(Deserialize)]
+pub struct PathMarker {
+    pub cell: (u32, u32),
+    //Degrees like sprite angles, multiples of 45.0 point at a neighbouring cell
+    pub angle: f32,
+}
+
+impl PathMarker {
+    pub fn heading(&self) -> Vector2<f32> {
+        Vector2::from_angle(self.angle)
+    }
+}
+
 #[derive(Deserialize)]
 #[repr(C)]
 struct WorldStructure {
@@ -18,18 +59,76 @@ struct WorldStructure {
     ceiling_idx: u32,
     floor_idx: u32,
 
-    //Sprite template name, position, angle
-    sprites: Vec<(String, Vector2<f32>, f32)>,
+    #[serde(default)]
+    sprites: Vec<Spawn>,
+
+    //Tile index, whether the tile blocks movement
+    //Masked tiles are see-through, rays pass them and draw whatever lies behind
+    #[serde(default)]
+    masked_tiles: Vec<(u32, bool)>,
+
+    //Tile index, height in wall units (0.5 is a half wall, 3.0 a tower of three stacked walls)
+    #[serde(default)]
+    tile_heights: Vec<(u32, f32)>,
+
+    //Tile indices of exits (elevator switches, ...), using one ends the level
+    #[serde(default)]
+    exit_tiles: Vec<u32>,
+
+    //Tile index, key that opens it. Using the tile with the key clears the cell
+    #[serde(default)]
+    locked_tiles: Vec<(u32, Key)>,
+
+    //x, y, tile shown on the floor of that cell instead of the floor of the map
+    #[serde(default)]
+    floor_tiles: Vec<(u32, u32, u32)>,
+
+    //Floor tile index, damage per second dealt to a player standing on it (lava, acid, ...)
+    #[serde(default)]
+    hazard_tiles: Vec<(u32, f32)>,
+
+    #[serde(default)]
+    player_starts: Vec<PlayerStart>,
+
+    #[serde(default)]
+    path_markers: Vec<PathMarker>,
+
+    //x, y of cells that count as a found secret once the player enters them
+    #[serde(default)]
+    secret_cells: Vec<(u32, u32)>,
 }
 
 impl WorldStructure {
+    fn load(identifier: &str) -> Result<WorldStructure, Box<dyn std::error::Error>> {
+        let layout_path = RES_MAPS.to_owned() + identifier + "/layout.ron";
+        let layout_file_content = fs::read_to_string(layout_path)?;
+        let mut layout: WorldStructure = ron::from_str(&layout_file_content)?;
+        layout.validate();
+
+        Ok(layout)
+    }
+
+    ///Width and height in cells
+    fn size(&self) -> (u32, u32) {
+        let stride = self.layout_stride;
+        (stride, self.layout.len() as u32 / stride.max(1))
+    }
+
+    fn nav_grid(&self, tile_properties: &[TileProperties]) -> NavGrid {
+        let (width, height) = self.size();
+        NavGrid::new(width, height, |x, y| {
+            blocks_movement(self.layout[(x + y * width) as usize], tile_properties)
+        })
+    }
+
     fn as_vec_for_gpu(&self) -> Vec<u32> {
         let mut data: Vec<u32> = Vec::new();
 
-        data.push(self.floor_idx);
         data.push(self.ceiling_idx);
+        data.push(self.floor_idx);
         data.push(self.layout_tile_width);
         data.push(self.layout_stride);
+        data.push(self.max_wall_height().to_bits());
 
         for tile in &self.layout {
             data.push(*tile);
@@ -37,18 +136,188 @@ impl WorldStructure {
 
         data
     }
+
+    ///Floor tile of every cell, 0 where the floor of the map is shown
+    fn floor_layer(&self) -> Vec<u32> {
+        let (width, height) = self.size();
+        let mut layer = vec![0; (width * height) as usize];
+
+        for (x, y, tile) in &self.floor_tiles {
+            if *x < width && *y < height {
+                layer[(x + y * width) as usize] = *tile;
+            } else {
+                eprintln!("Floor tile at ({}, {}) is outside of the map", x, y);
+            }
+        }
+
+        layer
+    }
+
+    ///Per tile index lookup table of the tile flags, indexed by the raw layout value
+    fn tile_properties(&self) -> Vec<TileProperties> {
+        let tile_count = self.layout.iter().copied().max().unwrap_or(0) as usize + 1;
+        let mut properties = vec![TileProperties::default(); tile_count];
+
+        for (tile, solid) in &self.masked_tiles {
+            match properties.get_mut(*tile as usize) {
+                Some(entry) => {
+                    entry.flags |= TILE_MASKED;
+                    if !*solid {
+                        entry.flags |= TILE_PASSABLE;
+                    }
+                }
+                None => eprintln!("Masked tile {} isn't used by the layout", tile),
+            }
+        }
+
+        for (tile, height) in &self.tile_heights {
+            match properties.get_mut(*tile as usize) {
+                Some(entry) => entry.height = height.max(0.0),
+                None => eprintln!("Tile {} with custom height isn't used by the layout", tile),
+            }
+        }
+
+        properties
+    }
+
+    ///Drops player starts outside of the map or inside of anything but empty cells,
+    ///same for path markers, which also warn if they send actors into a wall
+    fn validate(&mut self) {
+        let stride = self.layout_stride as usize;
+        let rows = self.layout.len() / stride.max(1);
+        let layout = &self.layout;
+
+        self.player_starts.retain(|start| {
+            let (x, y) = (start.position.x, start.position.y);
+            let inside = x >= 0.0 && y >= 0.0 && (x as usize) < stride && (y as usize) < rows;
+
+            if !inside {
+                eprintln!("Player start at ({}, {}) is outside of the map", x, y);
+                return false;
+            }
+
+            let tile = layout[x as usize + y as usize * stride];
+            if tile != 0 {
+                eprintln!(
+                    "Player start at ({}, {}) is inside of tile {}, it has to be on an empty cell",
+                    x, y, tile
+                );
+                return false;
+            }
+
+            true
+        });
+
+        let grid = self.nav_grid(&self.tile_properties());
+
+        self.path_markers.retain(|marker| {
+            let (x, y) = marker.cell;
+
+            if !grid.contains(x as i32, y as i32) {
+                eprintln!("Path marker at ({}, {}) is outside of the map", x, y);
+                return false;
+            }
+
+            if grid.is_blocked(marker.cell) {
+                eprintln!("Path marker at ({}, {}) is inside of a wall", x, y);
+                return false;
+            }
+
+            let step = navigation::grid_step(marker.heading());
+            let next = ((x as i32 + step.0) as u32, (y as i32 + step.1) as u32);
+            if !grid.neighbours(marker.cell).any(|(cell, _)| cell == next) {
+                eprintln!(
+                    "Path marker at ({}, {}) with angle {} leads into a wall",
+                    x, y, marker.angle
+                );
+            }
+
+            true
+        });
+
+        self.secret_cells.retain(|(x, y)| {
+            if !grid.contains(*x as i32, *y as i32) {
+                eprintln!("Secret at ({}, {}) is outside of the map", x, y);
+                return false;
+            }
+
+            if grid.is_blocked((*x, *y)) {
+                eprintln!("Secret at ({}, {}) is inside of a wall", x, y);
+                return false;
+            }
+
+            true
+        });
+        self.secret_cells.sort_unstable();
+        self.secret_cells.dedup();
+    }
+
+    ///Walls of this height hide everything behind them, rays stop there
+    fn max_wall_height(&self) -> f32 {
+        self.tile_heights
+            .iter()
+            .fold(1.0, |max: f32, (_, height)| max.max(*height))
+    }
+}
+
+const TILE_MASKED: u32 = 1;
+const TILE_PASSABLE: u32 = 2;
+
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct TileProperties {
+    flags: u32,
+    height: f32,
+}
+
+///Passable masked tiles (curtains, ...) don't block movement
+fn blocks_movement(tile: u32, tile_properties: &[TileProperties]) -> bool {
+    if tile == 0 {
+        return false;
+    }
+
+    match tile_properties.get(tile as usize) {
+        Some(properties) => properties.flags & TILE_PASSABLE == 0,
+        None => true,
+    }
 }
 
+impl Default for TileProperties {
+    fn default() -> Self {
+        TileProperties {
+            flags: 0,
+            height: 1.0,
+        }
+    }
+}
+
+//Has to match the slice layout in walls/preprocess.glsl
+//Per column: layer count followed by up to MAX_WALL_LAYERS layers of
+//line height, texture index, texture x and distance
+const MAX_WALL_LAYERS: isize = 6;
+const WALL_LAYER_SIZE: isize = 4;
+const WALL_SLICE_SIZE: isize = 1 + MAX_WALL_LAYERS * WALL_LAYER_SIZE;
+
 const RES_MAPS: &str = "./res/maps/";
 
 pub struct World {
     identifier: String,
     structure: WorldStructure,
+    //Layout as loaded from the map file, to find cells that changed since
+    original_layout: Vec<u32>,
+    //Counts layout changes, lets caches of the layout (navigation, ...) know they are outdated
+    revision: u32,
+    tile_properties: Vec<TileProperties>,
+    floor_layer: Vec<u32>,
     sprite_registry: SpriteRegistry,
+    //Circles of blocking sprites as center and radius, kept up to date by the entities
+    blockers: Vec<(Vector2<f32>, f32)>,
     _ssbo: SSBO,
+    _tile_ssbo: SSBO,
+    _floor_ssbo: SSBO,
 
+    //Size of the 3D view, the image itself is bound to image unit 0 by its owner
     resolution: (u32, u32),
-    framebuffer: Framebuffer,
     _gpu_slice_ssbo: SSBO,
     _gpu_caf_ssbo: SSBO,
     _gpu_z_ssbo: SSBO,
@@ -69,9 +338,7 @@ impl World {
     ) -> Result<World, Box<dyn std::error::Error>> {
         let full_path = RES_MAPS.to_owned() + identifier + "/";
 
-        let layout_path = String::from(full_path.clone() + "layout.ron");
-        let layout_file_content = fs::read_to_string(layout_path)?;
-        let layout: WorldStructure = ron::from_str(&layout_file_content)?;
+        let layout = WorldStructure::load(identifier)?;
 
         let world_tile_sheet_path = OsString::from(full_path.clone() + "sheet.ron");
         let world_tile_sheet_asset = SpriteAsset::load(&world_tile_sheet_path)?;
@@ -80,17 +347,16 @@ impl World {
         let layout_gpu = layout.as_vec_for_gpu();
         let _ssbo = SSBO::from(3, &layout_gpu, gl::STATIC_DRAW);
 
-        let sprite_registry = SpriteRegistry::create(
-            &world_tile_sheet,
-            &layout.sprites,
-            &(full_path + "sprites"),
-            player,
-        )?;
+        let tile_properties = layout.tile_properties();
+        let _tile_ssbo = SSBO::from(10, &tile_properties, gl::STATIC_DRAW);
 
-        let framebuffer = Framebuffer::create(0, resolution.0 as i32, resolution.1 as i32);
-        let gpu_slice_ssbo = SSBO::empty(5, 3 * resolution.0 as isize * gpu::INT, gl::DYNAMIC_DRAW);
-        let gpu_caf_ssbo = SSBO::empty(6, 4 * resolution.1 as isize * gpu::FLOAT, gl::DYNAMIC_DRAW);
-        let gpu_z_ssbo = SSBO::empty(7, resolution.0 as isize * gpu::DOUBLE, gl::DYNAMIC_DRAW);
+        let floor_layer = layout.floor_layer();
+        let _floor_ssbo = SSBO::from(12, &floor_layer, gl::STATIC_DRAW);
+
+        let sprite_registry =
+            SpriteRegistry::create(&world_tile_sheet, &(full_path + "sprites"), player)?;
+
+        let (gpu_slice_ssbo, gpu_caf_ssbo, gpu_z_ssbo) = Self::resolution_buffers(resolution);
 
         let pre_cf_shader = Shader::from(
             "./src/shader/ceiling_floor/preprocess.glsl",
@@ -114,11 +380,17 @@ impl World {
 
         let world = World {
             identifier: String::from(identifier),
+            original_layout: layout.layout.clone(),
+            revision: 0,
             structure: layout,
+            tile_properties,
+            floor_layer,
             sprite_registry,
+            blockers: Vec::new(),
             _ssbo,
+            _tile_ssbo,
+            _floor_ssbo,
             resolution,
-            framebuffer,
             _gpu_slice_ssbo: gpu_slice_ssbo,
             _gpu_caf_ssbo: gpu_caf_ssbo,
             _gpu_z_ssbo: gpu_z_ssbo,
@@ -133,18 +405,234 @@ impl World {
         Ok(world)
     }
 
+    ///Intermediate buffers of the render passes, their size depends on the resolution
+    fn resolution_buffers(resolution: (u32, u32)) -> (SSBO, SSBO, SSBO) {
+        let gpu_slice_ssbo = SSBO::empty(
+            5,
+            WALL_SLICE_SIZE * resolution.0 as isize * gpu::INT,
+            gl::DYNAMIC_DRAW,
+        );
+        let gpu_caf_ssbo = SSBO::empty(6, 4 * resolution.1 as isize * gpu::FLOAT, gl::DYNAMIC_DRAW);
+        let gpu_z_ssbo = SSBO::empty(7, resolution.0 as isize * gpu::DOUBLE, gl::DYNAMIC_DRAW);
+
+        (gpu_slice_ssbo, gpu_caf_ssbo, gpu_z_ssbo)
+    }
+
+    ///Reallocates everything depending on the size of the 3D view
+    pub fn resize(&mut self, resolution: (u32, u32)) {
+        if resolution == self.resolution {
+            return;
+        }
+
+        let (gpu_slice_ssbo, gpu_caf_ssbo, gpu_z_ssbo) = Self::resolution_buffers(resolution);
+
+        self.resolution = resolution;
+        self._gpu_slice_ssbo = gpu_slice_ssbo;
+        self._gpu_caf_ssbo = gpu_caf_ssbo;
+        self._gpu_z_ssbo = gpu_z_ssbo;
+    }
+
+    ///Identifiers of all map folders that contain a layout, sorted
+    pub fn available_maps() -> Vec<String> {
+        let entries = match fs::read_dir(RES_MAPS) {
+            Ok(entries) => entries,
+            Err(e) => {
+                eprintln!("Warning: Failed to list maps in {}: {}", RES_MAPS, e);
+                return Vec::new();
+            }
+        };
+
+        let mut maps: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.join("layout.ron").is_file())
+            .filter_map(|path| Some(path.file_name()?.to_str()?.to_owned()))
+            .collect();
+
+        maps.sort();
+
+        maps
+    }
+
     pub fn identifier(&self) -> &String {
         return &self.identifier;
     }
 
+    ///Width and height in cells
+    pub fn size(&self) -> (u32, u32) {
+        self.structure.size()
+    }
+
+    ///Size of the 3D view in pixels
+    pub fn resolution(&self) -> (u32, u32) {
+        self.resolution
+    }
+
+    pub fn revision(&self) -> u32 {
+        self.revision
+    }
+
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        let (width, height) = self.size();
+        x >= 0 && y >= 0 && (x as u32) < width && (y as u32) < height
+    }
+
     pub fn at(&self, x: u32, y: u32) -> &u32 {
         return &self.structure.layout[(x + y * self.structure.layout_stride) as usize];
     }
 
+    ///Whether the cell blocks movement, passable masked tiles (curtains, ...) don't
+    pub fn is_solid(&self, x: u32, y: u32) -> bool {
+        blocks_movement(*self.at(x, y), &self.tile_properties)
+    }
+
+    ///Replaces the circles blocking movement besides the solid cells
+    pub fn set_blockers(&mut self, blockers: Vec<(Vector2<f32>, f32)>) {
+        self.blockers = blockers;
+    }
+
+    ///Whether a step runs into a solid cell or a blocking sprite.
+    ///Steps away from a sprite are fine, e.g. if it was placed on top of the player
+    pub fn blocks_step(&self, from: Vector2<f32>, to: Vector2<f32>) -> bool {
+        let (x, y) = (to.x.floor() as i32, to.y.floor() as i32);
+        if !self.contains(x, y) || self.is_solid(x as u32, y as u32) {
+            return true;
+        }
+
+        self.blockers.iter().any(|(center, radius)| {
+            let distance = (to - *center).mag();
+            distance < *radius && distance < (from - *center).mag()
+        })
+    }
+
+    ///Which cells block movement, closed doors are solid tiles until they are replaced
+    pub fn nav_grid(&self) -> NavGrid {
+        self.structure.nav_grid(&self.tile_properties)
+    }
+
+    ///Navigation grid of a map file, without loading anything for the GPU
+    #[cfg(test)]
+    pub fn load_nav_grid(identifier: &str) -> Result<NavGrid, Box<dyn std::error::Error>> {
+        let layout = WorldStructure::load(identifier)?;
+        Ok(layout.nav_grid(&layout.tile_properties()))
+    }
+
+    ///Start for the player in `slot`, ones made for the difficulty are preferred over general ones
+    pub fn player_start(&self, difficulty: Difficulty, slot: u32) -> Option<&PlayerStart> {
+        let starts = self
+            .structure
+            .player_starts
+            .iter()
+            .filter(|s| s.slot == slot);
+
+        starts
+            .clone()
+            .find(|s| s.difficulty == Some(difficulty))
+            .or_else(|| starts.clone().find(|s| s.difficulty.is_none()))
+    }
+
+    ///Whether the cell hides what lies behind it, masked tiles can be seen through
+    pub fn blocks_sight(&self, x: u32, y: u32) -> bool {
+        let tile = *self.at(x, y) as usize;
+        if tile == 0 {
+            return false;
+        }
+
+        match self.tile_properties.get(tile) {
+            Some(properties) => properties.flags & TILE_MASKED == 0,
+            None => true,
+        }
+    }
+
+    ///Cells which differ from the map file as (x, y, tile)
+    pub fn tile_changes(&self) -> Vec<(u32, u32, u32)> {
+        let stride = self.structure.layout_stride;
+
+        self.structure
+            .layout
+            .iter()
+            .zip(&self.original_layout)
+            .enumerate()
+            .filter(|(_, (tile, original))| tile != original)
+            .map(|(i, (tile, _))| (i as u32 % stride, i as u32 / stride, *tile))
+            .collect()
+    }
+
+    ///Replaces cells given as (x, y, tile) and uploads the changed layout
+    pub fn set_tiles(&mut self, tiles: &[(u32, u32, u32)]) {
+        if tiles.is_empty() {
+            return;
+        }
+
+        let stride = self.structure.layout_stride;
+        for (x, y, tile) in tiles {
+            match self.structure.layout.get_mut((x + y * stride) as usize) {
+                Some(cell) if *x < stride => *cell = *tile,
+                _ => eprintln!("Cell ({}, {}) is outside of the map", x, y),
+            }
+        }
+
+        self.revision += 1;
+
+        //New tiles may lie beyond the old property table
+        self.tile_properties = self.structure.tile_properties();
+        self._tile_ssbo = SSBO::from(10, &self.tile_properties, gl::STATIC_DRAW);
+        self._ssbo = SSBO::from(3, &self.structure.as_vec_for_gpu(), gl::STATIC_DRAW);
+    }
+
+    ///Heading of the path marker in the cell
+    pub fn path_marker(&self, cell: Cell) -> Option<Vector2<f32>> {
+        self.structure
+            .path_markers
+            .iter()
+            .find(|marker| marker.cell == cell)
+            .map(PathMarker::heading)
+    }
+
+    ///Damage per second dealt by the floor of the cell
+    pub fn hazard(&self, x: u32, y: u32) -> Option<f32> {
+        let (width, _) = self.size();
+        let tile = *self.floor_layer.get((x + y * width) as usize)?;
+
+        self.structure
+            .hazard_tiles
+            .iter()
+            .find(|(hazard, _)| *hazard == tile && tile != 0)
+            .map(|(_, damage)| *damage)
+    }
+
+    ///Cells counting as a secret, each one is only listed once
+    pub fn secret_cells(&self) -> &[(u32, u32)] {
+        &self.structure.secret_cells
+    }
+
+    pub fn is_exit(&self, x: u32, y: u32) -> bool {
+        self.structure.exit_tiles.contains(self.at(x, y))
+    }
+
+    ///Key needed to open the locked door in the cell
+    pub fn lock(&self, x: u32, y: u32) -> Option<Key> {
+        let tile = *self.at(x, y);
+        self.structure
+            .locked_tiles
+            .iter()
+            .find(|(locked, _)| *locked == tile)
+            .map(|(_, key)| *key)
+    }
+
     pub fn sprites(&mut self) -> &mut SpriteRegistry {
         &mut self.sprite_registry
     }
 
+    pub fn sprite_registry(&self) -> &SpriteRegistry {
+        &self.sprite_registry
+    }
+
+    ///Entities placed by the map
+    pub fn spawns(&self) -> &[Spawn] {
+        &self.structure.sprites
+    }
+
     pub fn render(&mut self, player: &Player) {
         self.sprite_registry.update(player);
 
@@ -176,7 +664,10 @@ impl World {
             self.sprite_shader.set_uint("sprite_idx", i);
 
             let preprocess = self.sprite_registry.preprocess_result(i);
-            if preprocess.draw_end_x >= preprocess.draw_start_x && preprocess.transform_y > 0.0 {
+            if preprocess.draw_end_x >= preprocess.draw_start_x
+                && preprocess.draw_end_y >= preprocess.draw_start_y
+                && preprocess.transform_y > 0.0
+            {
                 let num_groups_x = (preprocess.draw_end_x - preprocess.draw_start_x) as u32;
                 let num_groups_y = (preprocess.draw_end_y - preprocess.draw_start_y) as u32;
 
@@ -188,7 +679,5 @@ impl World {
                 );
             }
         }
-
-        self.framebuffer.blit();
     }
 }
@@ -71,9 +71,11 @@ impl Framebuffer {
         };
     }
 
-    ///Display the framebuffer
-    pub fn blit(&self) {
+    ///Display the framebuffer, scaled to the given output size
+    pub fn blit(&self, target_x: i32, target_y: i32) {
         unsafe {
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.buffer_id);
+            gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, 0);
             gl::BlitFramebuffer(
                 0,
                 0,
@@ -81,8 +83,8 @@ impl Framebuffer {
                 self.res_y,
                 0,
                 0,
-                self.res_x,
-                self.res_y,
+                target_x,
+                target_y,
                 gl::COLOR_BUFFER_BIT,
                 gl::LINEAR,
             );
@@ -1,50 +1,91 @@
 mod engine;
 mod gpu;
 
-use crate::engine::{player, I18n, Player, Settings, Vector2, World};
+use crate::engine::{
+    check_coverage, player, Campaign, Font, Game, Gamepad, Hud, I18n, Menu, MenuAction, MenuInput,
+    Overlay, Player, SaveGame, Screen, Setting, Settings, Vector2, WeaponView, WindowMode, World,
+    QUICKSAVE_SLOT,
+};
+use crate::gpu::Framebuffer;
 
 use glfw::{Action, Context, Key};
+use std::sync::mpsc::Receiver;
 use std::time::Instant;
 
+const WINDOW_TITLE: &str = "Raster";
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let mut glfw = glfw::init(glfw::FAIL_ON_ERRORS)?;
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    if args.first().map(String::as_str) == Some("i18n-check") {
+        std::process::exit(if check_coverage() { 0 } else { 1 });
+    }
 
-    let mut settings = Settings::load();
+    let mut glfw = glfw::init(glfw::FAIL_ON_ERRORS)?;
+    let mut settings = Settings::load(&args);
 
-    let (mut window, events) = glfw
-        .create_window(
-            settings.resolution().0,
-            settings.resolution().1,
-            "Raster",
-            glfw::WindowMode::Windowed,
-        )
-        .expect("Failed to create GLFW window");
+    let (mut window, events) =
+        create_window(&mut glfw, &settings).expect("Failed to create GLFW window");
 
     window.set_key_polling(true);
+    window.set_framebuffer_size_polling(true);
+    window.set_cursor_pos_polling(true);
+    window.set_mouse_button_polling(true);
     window.make_current();
-    window.set_cursor_mode(glfw::CursorMode::Disabled);
     if window.uses_raw_mouse_motion() {
         window.set_raw_mouse_motion(true);
     }
 
     gl::load_with(|s| window.get_proc_address(s) as *const _);
 
-    settings.copy_to_gpu();
+    let (width, height) = window.get_framebuffer_size();
+    settings.resize((width as u32, height as u32));
+
+    if settings.vsync() {
+        glfw.set_swap_interval(glfw::SwapInterval::Sync(1));
+    } else {
+        glfw.set_swap_interval(glfw::SwapInterval::None);
+    }
 
-    glfw.set_swap_interval(glfw::SwapInterval::Sync(1));
+    let mut i18n = I18n::from(settings.language())?;
 
-    let i18n = I18n::from(settings.language())?;
+    let mut framebuffer = Framebuffer::create(0, 1, 1);
+    let font = Font::load("default")?;
+    let mut overlay = Overlay::load(settings.render_resolution())?;
+    let mut hud = Hud::load()?;
+    let mut weapon_view = WeaponView::load()?;
+    let campaign = Campaign::load().unwrap_or_else(|e| {
+        eprintln!(
+            "Couldn't load the campaign, maps are played on their own: {}",
+            e
+        );
+        Campaign::empty()
+    });
 
-    let mut player = Player::from(Vector2::new(2.0, 2.0));
+    let mut maps = World::available_maps();
+    campaign.sort_maps(&mut maps);
+    let mut menu = Menu::new(settings.render_resolution(), maps);
+    menu.set_saves(SaveGame::slots());
+    let mut gamepad = Gamepad::new();
+    let mut game: Option<Game> = None;
 
-    let mut world = World::load("test_map_2", *settings.resolution(), &player)?;
-    println!("Playing {}", i18n.get_translation(world.identifier()));
+    fit_to_resolution(
+        &mut settings,
+        &mut hud,
+        &mut weapon_view,
+        &mut framebuffer,
+        &mut overlay,
+        &mut menu,
+        None,
+    );
+    menu.open(Screen::Title);
 
     let mut delta_time: f32;
     let mut now = Instant::now();
 
     let mut mouse_delta = Vector2::new(0.0 as f32, 0.0);
     let mut mouse_pos = Vector2::new(0.0 as f32, 0.0);
+    let mut cursor_captured = false;
 
     while !window.should_close() {
         delta_time = now.elapsed().as_secs_f32();
@@ -52,17 +93,199 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
         //println!("{}", 1.0 / delta_time);
 
+        //The cursor is only used for looking around while playing
+        let playing = game.as_ref().is_some_and(|game| !game.is_finished()) && !menu.is_open();
+        let intermission = game.as_ref().is_some_and(Game::is_finished) && !menu.is_open();
+        let respawn = game.as_ref().is_some_and(Game::awaiting_respawn) && !menu.is_open();
+        if playing != cursor_captured {
+            cursor_captured = playing;
+            window.set_cursor_mode(if playing {
+                glfw::CursorMode::Disabled
+            } else {
+                glfw::CursorMode::Normal
+            });
+
+            let (mx, my) = window.get_cursor_pos();
+            mouse_pos.set(mx as f32, my as f32);
+        }
+
         let (mx, my) = window.get_cursor_pos();
         mouse_delta.set(mx as f32 - mouse_pos.x, my as f32 - mouse_pos.y);
         mouse_pos.set(mx as f32, my as f32);
 
-        player.update_position(&world, delta_time);
-        player.rotate_by_mouse(&mouse_delta, delta_time);
-        player.copy_to_gpu();
+        if let (Some(game), true) = (&mut game, playing) {
+            game.update(&mouse_delta, &settings, &i18n, delta_time);
+        }
+
+        let mut actions = Vec::new();
+        let mut inputs = gamepad.poll(&glfw);
 
         glfw.poll_events();
         for (_, event) in glfw::flush_messages(&events) {
-            handle_window_event(&mut window, event, &mut player);
+            match event {
+                //Minimized windows report a size of zero, there is nothing to render then
+                glfw::WindowEvent::FramebufferSize(width, height) if width > 0 && height > 0 => {
+                    settings.resize((width as u32, height as u32));
+                    fit_to_resolution(
+                        &mut settings,
+                        &mut hud,
+                        &mut weapon_view,
+                        &mut framebuffer,
+                        &mut overlay,
+                        &mut menu,
+                        game.as_mut(),
+                    );
+                }
+                glfw::WindowEvent::Key(key, _, Action::Press | Action::Repeat, _)
+                    if menu.is_open() || intermission || respawn =>
+                {
+                    inputs.extend(MenuInput::from_key(key));
+                }
+                glfw::WindowEvent::CursorPos(x, y) if menu.is_open() => {
+                    menu.hover(to_render_coords(&window, &settings, (x, y)), &font);
+                }
+                glfw::WindowEvent::MouseButton(glfw::MouseButtonLeft, Action::Press, _)
+                    if menu.is_open() =>
+                {
+                    let position = to_render_coords(&window, &settings, window.get_cursor_pos());
+                    actions.extend(menu.click(position, &font, &mut settings));
+                }
+                glfw::WindowEvent::Key(Key::Escape, _, Action::Press, _) => {
+                    inputs.push(MenuInput::Back);
+                }
+                glfw::WindowEvent::Key(Key::E, _, Action::Press, _) if playing => {
+                    if let Some(game) = &mut game {
+                        game.activate(&campaign, &i18n);
+                    }
+                }
+                glfw::WindowEvent::MouseButton(glfw::MouseButtonLeft, action, _) if playing => {
+                    if let Some(game) = &mut game {
+                        game.set_trigger(action != Action::Release);
+                    }
+                }
+                glfw::WindowEvent::Key(key, _, Action::Press, _)
+                    if playing && weapon_slot(key).is_some() =>
+                {
+                    if let (Some(game), Some(slot)) = (&mut game, weapon_slot(key)) {
+                        game.select_weapon(slot);
+                    }
+                }
+                glfw::WindowEvent::Key(Key::F5, _, Action::Press, _) if playing => {
+                    actions.push(MenuAction::Save(QUICKSAVE_SLOT));
+                }
+                glfw::WindowEvent::Key(Key::F9, _, Action::Press, _)
+                    if game.is_some() && !menu.is_open() =>
+                {
+                    actions.push(MenuAction::Load(QUICKSAVE_SLOT));
+                }
+                _ => {
+                    if let (Some(game), false) = (&mut game, menu.is_open()) {
+                        handle_window_event(event, game.player(), &mut settings);
+                    }
+                }
+            }
+        }
+
+        for input in inputs {
+            if menu.is_open() {
+                actions.extend(menu.handle(input, &mut settings));
+            } else if input == MenuInput::Back {
+                menu.open(Screen::Pause);
+                if let Some(game) = &mut game {
+                    game.player().stop_movement();
+                    game.set_trigger(false);
+                }
+            } else if let (MenuInput::Select, Some(current)) = (input, &mut game) {
+                if current.awaiting_respawn() {
+                    if let Err(e) = current.respawn(&settings, &i18n) {
+                        eprintln!("Couldn't restart the map: {}", e);
+                        game = None;
+                        menu.open(Screen::Title);
+                    }
+                    continue;
+                }
+
+                if !current.is_finished() {
+                    continue;
+                }
+
+                //The episode ends with its last map, then it's back to the title screen
+                match current.advance(&campaign, &settings, &i18n) {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        game = None;
+                        menu.open(Screen::Title);
+                    }
+                    Err(e) => {
+                        eprintln!("Couldn't load the next map: {}", e);
+                        game = None;
+                        menu.open(Screen::Title);
+                    }
+                }
+            }
+        }
+
+        for action in actions {
+            match action {
+                MenuAction::Play(map) => match Game::start(&map, &campaign, &settings, &i18n) {
+                    Ok(started) => game = Some(started),
+                    Err(e) => {
+                        eprintln!("Couldn't load map '{}': {}", map, e);
+                        menu.open(Screen::Title);
+                    }
+                },
+                MenuAction::Resume => {}
+                MenuAction::Save(slot) => {
+                    if let Some(game) = &mut game {
+                        save_game(game, slot, &i18n, &mut menu);
+                    }
+                }
+                MenuAction::Load(slot) => match Game::load(slot, &campaign, &settings, &i18n) {
+                    Ok(loaded) => game = Some(loaded),
+                    Err(e) => {
+                        eprintln!("Couldn't load save game {}: {}", slot, e);
+                        match &mut game {
+                            Some(game) => {
+                                game.notify(i18n.get_translation("load_failed").to_owned())
+                            }
+                            None => menu.open(Screen::Title),
+                        }
+                    }
+                },
+                MenuAction::QuitToTitle => game = None,
+                MenuAction::Quit => window.set_should_close(true),
+                MenuAction::Changed(Setting::Resolution) => {
+                    apply_resolution(&mut glfw, &mut window, &settings);
+                }
+                MenuAction::Changed(Setting::Language) => {
+                    match i18n.set_language(settings.language()) {
+                        Ok(()) => {
+                            if let Some(game) = &mut game {
+                                game.localise(&i18n);
+                            }
+                        }
+                        Err(e) => eprintln!("Couldn't switch language: {}", e),
+                    }
+                }
+                MenuAction::Changed(Setting::Fov) => {
+                    if let Some(game) = &mut game {
+                        game.player().set_fov(settings.fov());
+                    }
+                }
+                //Read whenever they are used, nothing to apply
+                MenuAction::Changed(
+                    Setting::MouseSensitivity
+                    | Setting::MasterVolume
+                    | Setting::MusicVolume
+                    | Setting::EffectsVolume,
+                ) => {}
+            }
+        }
+
+        let (width, height) = window.get_framebuffer_size();
+        if width == 0 || height == 0 {
+            glfw.wait_events();
+            continue;
         }
 
         unsafe {
@@ -70,19 +293,161 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             gl::Clear(gl::COLOR_BUFFER_BIT);
         }
 
-        world.render(&player);
+        if let Some(game) = &mut game {
+            game.render(&hud, &weapon_view, &mut overlay, &font, &i18n);
+        }
+
+        menu.draw(&mut overlay, &font, &i18n, &settings);
+
+        overlay.render();
+        framebuffer.blit(width, height);
         window.swap_buffers();
     }
 
+    if settings.modified() {
+        if let Err(e) = settings.save() {
+            eprintln!("Couldn't save settings: {}", e);
+        }
+    }
+
     Ok(())
 }
 
-fn handle_window_event(window: &mut glfw::Window, event: glfw::WindowEvent, player: &mut Player) {
-    match event {
-        glfw::WindowEvent::Key(Key::Escape, _, Action::Press, _) => {
-            window.set_cursor_mode(glfw::CursorMode::Normal);
+fn save_game(game: &mut Game, slot: u32, i18n: &I18n, menu: &mut Menu) {
+    match game.save(slot) {
+        Ok(()) => {
+            game.notify(i18n.get_translation("game_saved").to_owned());
+            menu.set_saves(SaveGame::slots());
+        }
+        Err(e) => {
+            eprintln!("Couldn't save the game: {}", e);
+            game.notify(i18n.get_translation("save_failed").to_owned());
         }
+    }
+}
 
+///Lays out everything for the current render resolution, the HUD takes its rows from the 3D view
+fn fit_to_resolution(
+    settings: &mut Settings,
+    hud: &mut Hud,
+    weapon_view: &mut WeaponView,
+    framebuffer: &mut Framebuffer,
+    overlay: &mut Overlay,
+    menu: &mut Menu,
+    game: Option<&mut Game>,
+) {
+    let resolution = settings.render_resolution();
+
+    if (framebuffer.res_x, framebuffer.res_y) != (resolution.0 as i32, resolution.1 as i32) {
+        *framebuffer = Framebuffer::create(0, resolution.0 as i32, resolution.1 as i32);
+    }
+
+    settings.set_status_bar_height(hud.resize(resolution));
+    overlay.resize(resolution);
+    menu.resize(resolution);
+    weapon_view.resize(settings.view_resolution());
+
+    if let Some(game) = game {
+        game.resize(settings.view_resolution());
+    }
+}
+
+///Converts window coordinates to pixels of the rendered image
+fn to_render_coords(
+    window: &glfw::Window,
+    settings: &Settings,
+    position: (f64, f64),
+) -> (f32, f32) {
+    let (window_width, window_height) = window.get_size();
+    let (width, height) = settings.render_resolution();
+
+    (
+        position.0 as f32 * width as f32 / window_width.max(1) as f32,
+        position.1 as f32 * height as f32 / window_height.max(1) as f32,
+    )
+}
+
+///Applies the configured resolution to the window, the framebuffer follows with a resize event
+fn apply_resolution(glfw: &mut glfw::Glfw, window: &mut glfw::Window, settings: &Settings) {
+    let (width, height) = *settings.resolution();
+
+    match settings.window_mode() {
+        WindowMode::Windowed => window.set_size(width as i32, height as i32),
+        WindowMode::Fullscreen => glfw.with_primary_monitor_mut(|_, monitor| {
+            if let Some(monitor) = monitor {
+                window.set_monitor(
+                    glfw::WindowMode::FullScreen(monitor),
+                    0,
+                    0,
+                    width,
+                    height,
+                    None,
+                );
+            }
+        }),
+        //Borderless always uses the desktop resolution
+        WindowMode::Borderless => {}
+    }
+}
+
+fn create_window(
+    glfw: &mut glfw::Glfw,
+    settings: &Settings,
+) -> Option<(glfw::Window, Receiver<(f64, glfw::WindowEvent)>)> {
+    let (width, height) = *settings.resolution();
+
+    match settings.window_mode() {
+        WindowMode::Windowed => {
+            glfw.create_window(width, height, WINDOW_TITLE, glfw::WindowMode::Windowed)
+        }
+        WindowMode::Fullscreen => glfw.with_primary_monitor(|glfw, monitor| {
+            let mode = monitor.map_or(glfw::WindowMode::Windowed, glfw::WindowMode::FullScreen);
+            glfw.create_window(width, height, WINDOW_TITLE, mode)
+        }),
+        //A fullscreen window matching the current video mode keeps the desktop resolution
+        WindowMode::Borderless => glfw.with_primary_monitor(|glfw, monitor| {
+            let monitor = monitor.and_then(|m| m.get_video_mode().map(|mode| (m, mode)));
+            match monitor {
+                Some((monitor, mode)) => {
+                    glfw.window_hint(glfw::WindowHint::RedBits(Some(mode.red_bits)));
+                    glfw.window_hint(glfw::WindowHint::GreenBits(Some(mode.green_bits)));
+                    glfw.window_hint(glfw::WindowHint::BlueBits(Some(mode.blue_bits)));
+                    glfw.window_hint(glfw::WindowHint::RefreshRate(Some(mode.refresh_rate)));
+
+                    glfw.create_window(
+                        mode.width,
+                        mode.height,
+                        WINDOW_TITLE,
+                        glfw::WindowMode::FullScreen(monitor),
+                    )
+                }
+                None => glfw.create_window(width, height, WINDOW_TITLE, glfw::WindowMode::Windowed),
+            }
+        }),
+    }
+}
+
+///Weapons are selected with the number keys, 1 is the first one
+fn weapon_slot(key: Key) -> Option<usize> {
+    let slots = [
+        Key::Num1,
+        Key::Num2,
+        Key::Num3,
+        Key::Num4,
+        Key::Num5,
+        Key::Num6,
+        Key::Num7,
+        Key::Num8,
+        Key::Num9,
+    ];
+
+    slots.iter().position(|slot| *slot == key)
+}
+
+const FOV_STEP: f32 = 5.0;
+
+fn handle_window_event(event: glfw::WindowEvent, player: &mut Player, settings: &mut Settings) {
+    match event {
         glfw::WindowEvent::Key(Key::W, _, Action::Press, _) => {
             player.start_movement(player::FORWARDS);
         }
@@ -110,6 +475,26 @@ fn handle_window_event(window: &mut glfw::Window, event: glfw::WindowEvent, play
         glfw::WindowEvent::Key(Key::A, _, Action::Release, _) => {
             player.end_movement(player::LEFT);
         }
+
+        glfw::WindowEvent::Key(Key::Space, _, Action::Press, _) => {
+            player.jump();
+        }
+
+        glfw::WindowEvent::Key(Key::LeftControl, _, Action::Press, _) => {
+            player.start_crouch();
+        }
+        glfw::WindowEvent::Key(Key::LeftControl, _, Action::Release, _) => {
+            player.end_crouch();
+        }
+
+        glfw::WindowEvent::Key(Key::Equal, _, Action::Press, _) => {
+            settings.set_fov(settings.fov() + FOV_STEP);
+            player.set_fov(settings.fov());
+        }
+        glfw::WindowEvent::Key(Key::Minus, _, Action::Press, _) => {
+            settings.set_fov(settings.fov() - FOV_STEP);
+            player.set_fov(settings.fov());
+        }
         _ => {}
     }
 }
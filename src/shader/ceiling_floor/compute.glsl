@@ -7,13 +7,23 @@ layout(rgba32f, binding = 0) uniform image2D img;
 layout(std430, binding = 1) buffer Settings {
 	uint resolution_x;
 	uint resolution_y;
+	uint view_offset_y;
 } settings;
 
+layout(std430, binding = 2) buffer PlayerData {
+	vec2 position;
+	vec2 direction;
+	vec2 camera_plane;
+	float pitch;
+	float eye_height;
+} player;
+
 layout(std430, binding = 3) buffer World {
 	uint ceiling_texture_idx;
 	uint floor_texture_idx;
 	uint geometry_tile_width;
 	uint stride;
+	float max_wall_height;
 	uint[] geometry;
 } world;
 
@@ -23,11 +33,37 @@ layout(std430, binding = 6) buffer CaFData {
 	float data[];
 } caf;
 
+//Floor tile of every cell, 0 shows the floor of the map
+layout(std430, binding = 12) buffer FloorLayer {
+	uint tiles[];
+} floor_layer;
+
+//Screen row of the horizon, the pitch shears it up and down
+float horizon()
+{
+	return float(settings.resolution_y) / 2.0 - player.pitch * float(settings.resolution_y);
+}
+
+//Pixels per world unit at distance 1, derived from the horizontal field of view
+float projection_scale()
+{
+	return float(settings.resolution_x) / (2.0 * length(player.camera_plane));
+}
+
+//Distance of the floor (below the horizon) or ceiling (above it) seen in the given row
+float row_distance(int y)
+{
+	float p = float(y) + 0.5 - horizon();
+
+	if (p < 0)
+		return player.eye_height * projection_scale() / -p;
+	else
+		return (1.0 - player.eye_height) * projection_scale() / p;
+}
+
 float line_to_lod(int y)
 {
-	int p = y - int(settings.resolution_y) / 2;
-	float pos_z = 0.5 * float(settings.resolution_y);
-	float row_dist = pos_z / p;
+	float row_dist = row_distance(y);
 
 	float lod = 0.0;
 
@@ -63,18 +99,24 @@ void compute(ivec2 iCoords)
 	int tx = int(world.geometry_tile_width * (floor_x - cell_x)) & int(world.geometry_tile_width - 1);
 	int ty = int(world.geometry_tile_width * (floor_y - cell_y)) & int(world.geometry_tile_width - 1);
 
-	int floor_tex_idx = int(world.floor_texture_idx);
-	int ceiling_tex_idx = int(world.ceiling_texture_idx);
+	//Rows below the horizon show the floor, the ones above it the ceiling
+	int tex_idx;
+	if (float(iCoords.y) + 0.5 < horizon()) {
+		tex_idx = int(world.floor_texture_idx);
 
-	//floor
-	vec4 color = get_atlas_color(floor_tex_idx - 1, tx, ty, line_to_lod(iCoords.y));
+		int rows = floor_layer.tiles.length() / int(world.stride);
+		if (cell_x >= 0 && cell_y >= 0 && cell_x < int(world.stride) && cell_y < rows) {
+			uint tile = floor_layer.tiles[cell_y * int(world.stride) + cell_x];
+			if (tile != 0)
+				tex_idx = int(tile);
+		}
+	} else {
+		tex_idx = int(world.ceiling_texture_idx);
+	}
 
-	imageStore(img, iCoords, color);
-	//ceiling
-	color = get_atlas_color(ceiling_tex_idx - 1, tx, ty, line_to_lod(iCoords.y));
-	ivec2 pos = ivec2(iCoords.x, settings.resolution_y - iCoords.y - 1);
+	vec4 color = get_atlas_color(tex_idx - 1, tx, ty, line_to_lod(iCoords.y));
 
-	imageStore(img, pos, color);
+	imageStore(img, iCoords + ivec2(0, settings.view_offset_y), color);
 }
 
 void main()
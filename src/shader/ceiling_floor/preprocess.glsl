@@ -6,12 +6,15 @@ layout(local_size_x = 1, local_size_y = 1) in;
 layout(std430, binding = 1) buffer Settings {
 	uint resolution_x;
 	uint resolution_y;
+	uint view_offset_y;
 } settings;
 
 layout(std430, binding = 2) buffer PlayerData {
 	vec2 position;
 	vec2 direction;
 	vec2 camera_plane;
+	float pitch;
+	float eye_height;
 } player;
 
 layout(std430, binding = 3) buffer World {
@@ -19,6 +22,7 @@ layout(std430, binding = 3) buffer World {
 	uint floor_texture_idx;
 	uint geometry_tile_width;
 	uint stride;
+	float max_wall_height;
 	uint[] geometry;
 } world;
 
@@ -26,6 +30,29 @@ layout(std430, binding = 6) buffer CaFData {
 	float data[];
 } caf;
 
+//Screen row of the horizon, the pitch shears it up and down
+float horizon()
+{
+	return float(settings.resolution_y) / 2.0 - player.pitch * float(settings.resolution_y);
+}
+
+//Pixels per world unit at distance 1, derived from the horizontal field of view
+float projection_scale()
+{
+	return float(settings.resolution_x) / (2.0 * length(player.camera_plane));
+}
+
+//Distance of the floor (below the horizon) or ceiling (above it) seen in the given row
+float row_distance(int y)
+{
+	float p = float(y) + 0.5 - horizon();
+
+	if (p < 0)
+		return player.eye_height * projection_scale() / -p;
+	else
+		return (1.0 - player.eye_height) * projection_scale() / p;
+}
+
 void calculate_caf(int y)
 {
 	float ray_dir_x0 = player.direction.x - player.camera_plane.x;
@@ -33,11 +60,7 @@ void calculate_caf(int y)
 	float ray_dir_x1 = player.direction.x + player.camera_plane.x;
 	float ray_dir_y1 = player.direction.y + player.camera_plane.y;
 
-	int p = y - int(settings.resolution_y) / 2;
-
-	float pos_z = 0.5 * float(settings.resolution_y);
-
-	float row_dist = pos_z / p;
+	float row_dist = row_distance(y);
 
 	float floor_step_x = row_dist * (ray_dir_x1 - ray_dir_x0) / settings.resolution_x;
 	float floor_step_y = row_dist * (ray_dir_y1 - ray_dir_y0) / settings.resolution_x;
@@ -5,12 +5,15 @@ layout(rgba32f, binding = 0) uniform image2D img;
 layout(std430, binding = 1) buffer Settings {
 	int resolution_x;
 	int resolution_y;
+	int view_offset_y;
 } settings;
 
 layout(std430, binding = 2) buffer PlayerData {
 	vec2 position;
 	vec2 direction;
 	vec2 camera_plane;
+	float pitch;
+	float eye_height;
 } player;
 
 layout(std430, binding = 3) buffer World {
@@ -18,11 +21,21 @@ layout(std430, binding = 3) buffer World {
 	uint floor_texture_idx;
 	uint geometry_tile_width;
 	uint stride;
+	float max_wall_height;
 	uint[] geometry;
 } world;
 
 layout(binding = 4) uniform sampler2DArray tex_atlas;
 
+//Has to match the layout written by walls/preprocess.glsl
+#define MAX_LAYERS 6
+#define LAYER_SIZE 4
+#define SLICE_SIZE (1 + MAX_LAYERS * LAYER_SIZE)
+
+layout(std430, binding = 5) buffer SliceData {
+	int data[];
+} slice;
+
 layout(std430, binding = 7) buffer zData {
 	double data[];
 } z_buffer;
@@ -39,6 +52,11 @@ struct Sprite {
 	int	tile_width;
 	int	tile_height;
 
+	uint	flags;
+	float	scale_x;
+	float	scale_y;
+	float	offset;
+
 	int	animation_index;
 	int	animation_tick_period;
 	int	animation_tick;
@@ -58,6 +76,7 @@ struct Sprite_Preprocess_Result {
 	int	draw_end_x;
 
 	int	sprite_screen_x;
+	int	sprite_bottom;
 	double	transform_y;
 };
 
@@ -66,6 +85,21 @@ layout(std430, binding = 9) buffer Sprite_Preprocess_Results {
 	Sprite_Preprocess_Result results[];
 } sprite_preprocess;
 
+//Has to match the flags of SpriteTemplate
+#define SPRITE_FIXED 4u
+#define SPRITE_CEILING 8u
+
+#define TILE_MASKED 1u
+
+struct Tile_Properties {
+	uint	flags;
+	float	height;
+};
+
+layout(std430, binding = 10) buffer TileData {
+	Tile_Properties list[];
+} tiles;
+
 uniform uint sprite_idx;
 
 #define M_PI 3.141592654
@@ -98,43 +132,167 @@ vec4 get_atlas_color(Sprite sprite, int view_angle_idx, int x, int y, float lod)
 		lod);
 }
 
+vec4 get_wall_color(int texture_idx, int x, int y)
+{
+	float x_n = float(x) / float(world.geometry_tile_width);
+	float y_n = float(y) / float(world.geometry_tile_width);
+
+	float x_center_offset = 1.0 / (float(world.geometry_tile_width) * 2.0);
+	float y_center_offset = 1.0 / (float(world.geometry_tile_width) * 2.0);
+
+	return textureLod(tex_atlas, vec3(x_n + x_center_offset, y_n + y_center_offset, texture_idx), 0.0);
+}
+
+//Screen row of the horizon, the pitch shears it up and down
+float horizon()
+{
+	return float(settings.resolution_y) / 2.0 - player.pitch * float(settings.resolution_y);
+}
+
+//Pixels per world unit at distance 1, derived from the horizontal field of view
+float projection_scale()
+{
+	return float(settings.resolution_x) / (2.0 * length(player.camera_plane));
+}
+
+vec4 blend(vec4 color, vec4 base)
+{
+	return (color * color.a) + (base * (1 - color.a));
+}
+
+//Wall layers closer than the sprite either hide the pixel or, if masked, have to be drawn over it again
+vec4 apply_wall_layers(ivec2 iCoords, double sprite_dist, vec4 color, out bool hidden)
+{
+	hidden = false;
+	int layer_count = slice.data[iCoords.x * SLICE_SIZE];
+
+	for (int layer = layer_count - 1; layer >= 0; layer--) {
+		int base = iCoords.x * SLICE_SIZE + 1 + layer * LAYER_SIZE;
+
+		int line_height = slice.data[base + 0];
+		int tex_idx = slice.data[base + 1];
+		int tex_x = slice.data[base + 2];
+		float dist = intBitsToFloat(slice.data[base + 3]);
+
+		if (dist >= sprite_dist)
+			continue;
+
+		Tile_Properties properties = tiles.list[tex_idx + 1];
+
+		int draw_start = int(horizon() - player.eye_height * line_height);
+		int draw_end = draw_start + int(line_height * properties.height);
+
+		if (iCoords.y < draw_start || iCoords.y >= draw_end)
+			continue;
+
+		if ((properties.flags & TILE_MASKED) == 0) {
+			hidden = true;
+			return color;
+		}
+
+		double tex_step = 1.0 * int(world.geometry_tile_width) / line_height;
+		int tex_y = int((iCoords.y - draw_start) * tex_step) & (int(world.geometry_tile_width) - 1);
+
+		color = blend(get_wall_color(tex_idx, tex_x, tex_y), color);
+	}
+
+	return color;
+}
+
 float map(float value, float in_min, float in_max, float out_min, float out_max)
 {
 	return out_min + (out_max - out_min) * (value - in_min) / (in_max - in_min);
 }
 
+float cross2(vec2 a, vec2 b)
+{
+	return a.x * b.y - a.y * b.x;
+}
+
+//Texel of a fixed sprite seen through the column, the flat sprite is cut by the ray of the column.
+//Returns false if the ray misses it or the pixel is above or below it
+bool fixed_texel(Sprite sprite, ivec2 iCoords, out double dist, out ivec2 tex)
+{
+	float camera_x = 2.0 * float(iCoords.x) / float(settings.resolution_x) - 1.0;
+	vec2 ray = player.direction + player.camera_plane * camera_x;
+
+	float width = sprite.scale_x * float(sprite.tile_width) / float(sprite.tile_height);
+	vec2 span = normalize(vec2(-sprite.y_dir, sprite.x_dir)) * width;
+	vec2 to_start = vec2(sprite.x_pos, sprite.y_pos) - span / 2.0 - player.position;
+
+	float denominator = cross2(ray, span);
+	if (abs(denominator) < 0.000001)
+		return false;
+
+	//Perpendicular distance like the z buffer, and how far along the sprite the ray crosses it
+	float t = cross2(to_start, span) / denominator;
+	float u = cross2(to_start, ray) / denominator;
+	if (t <= 0 || u < 0 || u >= 1)
+		return false;
+
+	float line_height = projection_scale() / t;
+	float base = sprite.offset;
+	if ((sprite.flags & SPRITE_CEILING) != 0)
+		base += 1.0 - sprite.scale_y;
+
+	float bottom = horizon() + (base - player.eye_height) * line_height;
+	float height = line_height * sprite.scale_y;
+	if (iCoords.y < bottom || iCoords.y >= bottom + height)
+		return false;
+
+	dist = t;
+	tex = ivec2(int(u * sprite.tile_width), int((iCoords.y - bottom) * sprite.tile_height / height));
+	return true;
+}
+
 void main()
 {
 	ivec2 iCoords = ivec2(gl_GlobalInvocationID.xy);
 
 	Sprite sprite = sprites.list[sprite_idx];
+	Sprite_Preprocess_Result preprocess = sprite_preprocess.results[sprite_idx];
 
-	float dx = player.position.x - sprite.x_pos;
-	float dy = player.position.y - sprite.y_pos;
+	iCoords += ivec2(preprocess.draw_start_x, preprocess.draw_start_y);
 
-	float angle_to_player = atan(dy, dx) - atan(sprite.y_dir, sprite.x_dir) - (M_PI / sprite.view_angle_count);
+	double dist;
+	ivec2 tex;
+	//Fixed sprites are flat and always show their first row
+	int view_angle_idx = 0;
 
-	//move angle_to_player to the range [0,2PI)
-	if (angle_to_player < 0)
-		angle_to_player += 2 * M_PI;
+	if ((sprite.flags & SPRITE_FIXED) != 0) {
+		if (!fixed_texel(sprite, iCoords, dist, tex))
+			return;
+	} else {
+		float dx = player.position.x - sprite.x_pos;
+		float dy = player.position.y - sprite.y_pos;
 
-	int view_angle_idx = int(map(angle_to_player, 2 * M_PI, 0, 0, sprite.view_angle_count));
+		float angle_to_player = atan(dy, dx) - atan(sprite.y_dir, sprite.x_dir) - (M_PI / sprite.view_angle_count);
 
-	Sprite_Preprocess_Result preprocess = sprite_preprocess.results[sprite_idx];
+		//move angle_to_player to the range [0,2PI)
+		if (angle_to_player < 0)
+			angle_to_player += 2 * M_PI;
 
-	iCoords += ivec2(preprocess.draw_start_x, preprocess.draw_start_y);
+		view_angle_idx = int(map(angle_to_player, 2 * M_PI, 0, 0, sprite.view_angle_count));
 
-	if (preprocess.transform_y < z_buffer.data[iCoords.x]) {
-		int d = iCoords.y * 256 - settings.resolution_y * 128 + preprocess.sprite_height * 128;
+		int d = (iCoords.y - preprocess.sprite_bottom) * 256;
 
-		int tex_x = int(256 * (iCoords.x - (-preprocess.sprite_width / 2 + preprocess.sprite_screen_x)) * sprite.tile_width / preprocess.sprite_width) / 256;
-		int tex_y = int(((d * sprite.tile_height) / preprocess.sprite_height) / 256);
+		dist = preprocess.transform_y;
+		tex.x = int(256 * (iCoords.x - (-preprocess.sprite_width / 2 + preprocess.sprite_screen_x)) * sprite.tile_width / preprocess.sprite_width) / 256;
+		tex.y = int(((d * sprite.tile_height) / preprocess.sprite_height) / 256);
+	}
 
-		vec4 color = get_atlas_color(sprite, view_angle_idx, tex_x, tex_y, line_height_to_lod(preprocess.draw_end_y - preprocess.draw_start_y));
-		vec4 base = imageLoad(img, iCoords);
+	if (dist < z_buffer.data[iCoords.x]) {
+		vec4 color = get_atlas_color(sprite, view_angle_idx, tex.x, tex.y, line_height_to_lod(int(projection_scale() / dist)));
+		vec4 base = imageLoad(img, iCoords + ivec2(0, settings.view_offset_y));
 
-		color = (color * color.a) + (base * (1 - color.a));
+		//The base already contains the masked layers, only covered pixels need them reapplied
+		if (color.a > 0) {
+			bool hidden;
+			color = blend(color, base);
+			color = apply_wall_layers(iCoords, dist, color, hidden);
 
-		imageStore(img, iCoords, color);
+			if (!hidden)
+				imageStore(img, iCoords + ivec2(0, settings.view_offset_y), color);
+		}
 	}
 }
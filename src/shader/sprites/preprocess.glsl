@@ -5,12 +5,15 @@ layout(rgba32f, binding = 0) uniform image2D img;
 layout(std430, binding = 1) buffer Settings {
 	int resolution_x;
 	int resolution_y;
+	int view_offset_y;
 } settings;
 
 layout(std430, binding = 2) buffer PlayerData {
 	vec2 position;
 	vec2 direction;
 	vec2 camera_plane;
+	float pitch;
+	float eye_height;
 } player;
 
 struct Sprite {
@@ -25,6 +28,11 @@ struct Sprite {
 	int	tile_width;
 	int	tile_height;
 
+	uint	flags;
+	float	scale_x;
+	float	scale_y;
+	float	offset;
+
 	int	animation_index;
 	int	animation_tick_period;
 	int	animation_tick;
@@ -44,6 +52,7 @@ struct Sprite_Preprocess_Result {
 	int	draw_end_x;
 
 	int	sprite_screen_x;
+	int	sprite_bottom;
 	double	transform_y;
 };
 
@@ -52,40 +61,131 @@ layout(std430, binding = 9) buffer Sprite_Preprocess_Results {
 	Sprite_Preprocess_Result results[];
 } sprite_preprocess;
 
-void main()
+//Has to match the flags of SpriteTemplate
+#define SPRITE_FIXED 4u
+#define SPRITE_CEILING 8u
+
+//Screen row of the horizon, the pitch shears it up and down
+float horizon()
 {
-	ivec3 iCoords = ivec3(gl_GlobalInvocationID.xyz);
+	return float(settings.resolution_y) / 2.0 - player.pitch * float(settings.resolution_y);
+}
 
-	double sprite_x = sprites.list[iCoords.z].x_pos - player.position.x;
-	double sprite_y = sprites.list[iCoords.z].y_pos - player.position.y;
+//Pixels per world unit at distance 1, derived from the horizontal field of view
+float projection_scale()
+{
+	return float(settings.resolution_x) / (2.0 * length(player.camera_plane));
+}
+
+//Fixed sprites closer than this are cut off
+#define NEAR_PLANE 0.01
+
+//Position relative to the player in camera space, y is the depth
+dvec2 to_camera(vec2 position)
+{
+	double x = position.x - player.position.x;
+	double y = position.y - player.position.y;
 
 	double inv_det = 1.0 / (player.camera_plane.x * player.direction.y - player.direction.x * player.camera_plane.y);
 
-	double transform_x = inv_det * (player.direction.y * sprite_x - player.direction.x * sprite_y);
-	double transform_y = inv_det * (-player.camera_plane.y * sprite_x + player.camera_plane.x * sprite_y);
+	return dvec2(
+		inv_det * (player.direction.y * x - player.direction.x * y),
+		inv_det * (-player.camera_plane.y * x + player.camera_plane.x * y));
+}
+
+int screen_x(dvec2 transform)
+{
+	return int((settings.resolution_x / 2) * (1 + transform.x / transform.y));
+}
+
+//Width in wall units
+float sprite_units_wide(Sprite sprite)
+{
+	return sprite.scale_x * float(sprite.tile_width) / float(sprite.tile_height);
+}
 
-	int sprite_screen_x = int((settings.resolution_x / 2) * (1 + transform_x / transform_y));
+//Screen row of the bottom edge, line_height is the height of a wall at the same distance
+int sprite_bottom(Sprite sprite, double line_height)
+{
+	float base = sprite.offset;
+	if ((sprite.flags & SPRITE_CEILING) != 0)
+		base += 1.0 - sprite.scale_y;
 
-	int sprite_width = abs(int(settings.resolution_y / transform_y));
-	int sprite_height = abs(int(settings.resolution_y / transform_y));
+	return int(horizon() + (base - player.eye_height) * line_height);
+}
 
-	int draw_start_y = -sprite_height / 2 + settings.resolution_y / 2;
+void main()
+{
+	ivec3 iCoords = ivec3(gl_GlobalInvocationID.xyz);
+
+	Sprite sprite = sprites.list[iCoords.z];
+	vec2 center = vec2(sprite.x_pos, sprite.y_pos);
+
+	double transform_y;
+	//Depth of the far edge of fixed sprites
+	double far_y;
+	int sprite_screen_x;
+	int sprite_width;
+	int draw_start_x;
+	int draw_end_x;
+
+	if ((sprite.flags & SPRITE_FIXED) != 0) {
+		//Flat across the direction, the edges are projected on their own
+		vec2 half_span = normalize(vec2(-sprite.y_dir, sprite.x_dir)) * sprite_units_wide(sprite) / 2.0;
+		dvec2 a = to_camera(center - half_span);
+		dvec2 b = to_camera(center + half_span);
+
+		if (a.y < NEAR_PLANE && b.y < NEAR_PLANE) {
+			sprite_preprocess.results[iCoords.z] = Sprite_Preprocess_Result(0, 0, 0, -1, 0, -1, 0, 0, -1.0lf);
+			return;
+		}
+
+		if (a.y < NEAR_PLANE)
+			a = mix(a, b, (NEAR_PLANE - a.y) / (b.y - a.y));
+		if (b.y < NEAR_PLANE)
+			b = mix(b, a, (NEAR_PLANE - b.y) / (a.y - b.y));
+
+		//The closer edge is the tallest part
+		transform_y = min(a.y, b.y);
+		far_y = max(a.y, b.y);
+		draw_start_x = min(screen_x(a), screen_x(b));
+		draw_end_x = max(screen_x(a), screen_x(b));
+		sprite_width = draw_end_x - draw_start_x;
+		sprite_screen_x = (draw_start_x + draw_end_x) / 2;
+	} else {
+		dvec2 transform = to_camera(center);
+		transform_y = transform.y;
+		far_y = transform.y;
+		sprite_screen_x = screen_x(transform);
+
+		sprite_width = abs(int(projection_scale() / transform_y * sprite_units_wide(sprite)));
+		draw_start_x = -sprite_width / 2 + sprite_screen_x;
+		draw_end_x = sprite_width / 2 + sprite_screen_x;
+	}
+
+	//Same scale as the walls, a sprite of scale 1 is exactly one wall unit high
+	double line_height = abs(projection_scale() / transform_y);
+	int sprite_height = int(line_height * sprite.scale_y);
+
+	//Seen from the players eye height, standing on the floor or hanging from the ceiling, moved by the offset
+	int bottom = sprite_bottom(sprite, line_height);
+
+	//Fixed sprites away from the eye height reach further towards the horizon at their far edge
+	double far_line_height = abs(projection_scale() / far_y);
+	int far_bottom = sprite_bottom(sprite, far_line_height);
+
+	int draw_start_y = min(bottom, far_bottom);
+	int draw_end_y = max(bottom + sprite_height, far_bottom + int(far_line_height * sprite.scale_y));
 
 	if (draw_start_y < 0)
 		draw_start_y = 0;
 
-	int draw_end_y = sprite_height / 2 + settings.resolution_y / 2;
-
 	if (draw_end_y >= settings.resolution_y)
 		draw_end_y = settings.resolution_y - 1;
 
-	int draw_start_x = -sprite_width / 2 + sprite_screen_x;
-
 	if (draw_start_x < 0)
 		draw_start_x = 0;
 
-	int draw_end_x = sprite_width / 2 + sprite_screen_x;
-
 	if (draw_end_x >= settings.resolution_x)
 		draw_end_x = settings.resolution_x - 1;
 
@@ -99,6 +199,7 @@ void main()
 		draw_end_x,
 
 		sprite_screen_x,
+		bottom,
 		transform_y
 		);
 }
@@ -0,0 +1,54 @@
+#version 430
+layout(local_size_x = 1, local_size_y = 1) in;
+layout(rgba32f, binding = 0) uniform image2D img;
+
+//Rebound for every batch, all quads of a batch share the texture
+layout(binding = 11) uniform sampler2DArray atlas;
+
+//Has to match OverlayQuad in ui/overlay.rs
+struct Overlay_Quad {
+	int	screen_x;
+	int	screen_y;
+	int	atlas_x;
+	int	atlas_y;
+	int	width;
+	int	height;
+	float	scale_x;
+	float	scale_y;
+	vec4	color;
+};
+
+layout(std430, binding = 11) buffer Overlay_Quads {
+	Overlay_Quad list[];
+} quads;
+
+uniform uint quad_idx;
+
+//One invocation per pixel of the scaled quad, every quad is dispatched on its own
+void main()
+{
+	Overlay_Quad quad = quads.list[quad_idx];
+	ivec2 offset = ivec2(gl_GlobalInvocationID.xy);
+
+	ivec2 texel = ivec2(vec2(offset) / vec2(quad.scale_x, quad.scale_y));
+
+	if (texel.x >= quad.width || texel.y >= quad.height)
+		return;
+
+	ivec2 iCoords = ivec2(quad.screen_x, quad.screen_y) + offset;
+
+	if (any(lessThan(iCoords, ivec2(0))) || any(greaterThanEqual(iCoords, imageSize(img))))
+		return;
+
+	//Quads without a texture are filled with their color
+	vec4 color = quad.color;
+	if (quad.atlas_x >= 0)
+		color *= texelFetch(atlas, ivec3(quad.atlas_x + texel.x, quad.atlas_y + texel.y, 0), 0);
+
+	if (color.a <= 0)
+		return;
+
+	vec4 base = imageLoad(img, iCoords);
+
+	imageStore(img, iCoords, vec4(color.rgb * color.a + base.rgb * (1 - color.a), 1.0));
+}
@@ -7,22 +7,48 @@ layout(rgba32f, binding = 0) uniform image2D img;
 layout(std430, binding = 1) buffer Settings {
 	uint resolution_x;
 	uint resolution_y;
+	uint view_offset_y;
 } settings;
 
+layout(std430, binding = 2) buffer PlayerData {
+	vec2 position;
+	vec2 direction;
+	vec2 camera_plane;
+	float pitch;
+	float eye_height;
+} player;
+
 layout(std430, binding = 3) buffer World {
 	uint ceiling_texture_idx;
 	uint floor_texture_idx;
 	uint geometry_tile_width;
 	uint stride;
+	float max_wall_height;
 	uint[] geometry;
 } world;
 
 layout(binding = 4) uniform sampler2DArray tex_atlas;
 
+//Has to match the layout written by walls/preprocess.glsl
+#define MAX_LAYERS 6
+#define LAYER_SIZE 4
+#define SLICE_SIZE (1 + MAX_LAYERS * LAYER_SIZE)
+
 layout(std430, binding = 5) buffer SliceData {
 	int data[];
 } slice;
 
+#define TILE_MASKED 1u
+
+struct Tile_Properties {
+	uint	flags;
+	float	height;
+};
+
+layout(std430, binding = 10) buffer TileData {
+	Tile_Properties list[];
+} tiles;
+
 float line_height_to_lod(int line_height)
 {
 	double perp_wall_dist = double(settings.resolution_x) / double(line_height);
@@ -45,38 +71,72 @@ vec4 get_atlas_color(int texture_idx, int x, int y, float lod)
 	return textureLod(tex_atlas, vec3(x_n + x_center_offset, y_n + y_center_offset, texture_idx), lod);
 }
 
-vec4 process_slice(ivec2 iCoords)
+//Screen row of the horizon, the pitch shears it up and down
+float horizon()
+{
+	return float(settings.resolution_y) / 2.0 - player.pitch * float(settings.resolution_y);
+}
+
+vec4 blend(vec4 color, vec4 base)
+{
+	return (color * color.a) + (base * (1 - color.a));
+}
+
+//Draws one wall layer over the given color, pixels outside of the layer are left untouched
+vec4 process_layer(ivec2 iCoords, int layer, vec4 color)
 {
-	int x = iCoords.x;
+	int base = iCoords.x * SLICE_SIZE + 1 + layer * LAYER_SIZE;
+
+	int line_height = slice.data[base + 0];
+	int tex_idx = slice.data[base + 1];
+	int texX = slice.data[base + 2];
 
-	int line_height = slice.data[x * 3 + 0];
-	int tex_idx = slice.data[x * 3 + 1];
-	int texX = slice.data[x * 3 + 2];
+	Tile_Properties properties = tiles.list[tex_idx + 1];
 
-	int draw_start = int(-line_height / 2.0 + int(settings.resolution_y) / 2.0);
+	//Walls stand on the floor and grow upwards, one texture repetition per wall unit
+	int wall_bottom = int(horizon() - player.eye_height * line_height);
+	int draw_start = wall_bottom;
+	int draw_end = wall_bottom + int(line_height * properties.height);
 
 	if (draw_start < 0) draw_start = 0;
-	int draw_end = int(line_height / 2.0 + int(settings.resolution_y) / 2.0);
 
 	if (draw_end >= settings.resolution_y) draw_end = int(settings.resolution_y - 1);
 
 	int y = iCoords.y;
 	double tex_step = 1.0 * int(world.geometry_tile_width) / line_height;
-	double tex_pos = (y - settings.resolution_y / 2 + line_height / 2) * tex_step;
+	double tex_pos = (y - wall_bottom) * tex_step;
 
 	if (y < draw_start || y >= draw_end) {
-		return imageLoad(img, iCoords);
+		return color;
+	}
+
+	int texY = int(tex_pos) & (int(world.geometry_tile_width) - 1);
+	vec4 tex_color = get_atlas_color(int(tex_idx), texX, texY, line_height_to_lod(line_height));
+
+	if ((properties.flags & TILE_MASKED) != 0) {
+		return blend(tex_color, color);
 	} else {
-		int texY = int(tex_pos) & (int(world.geometry_tile_width) - 1);
-		return get_atlas_color(int(tex_idx), texX, texY, line_height_to_lod(line_height));
+		return tex_color;
 	}
 }
 
+vec4 process_slice(ivec2 iCoords)
+{
+	vec4 color = imageLoad(img, iCoords + ivec2(0, settings.view_offset_y));
+
+	//Back to front, so closer layers are drawn over whatever is behind them
+	int layer_count = slice.data[iCoords.x * SLICE_SIZE];
+	for (int layer = layer_count - 1; layer >= 0; layer--)
+		color = process_layer(iCoords, layer, color);
+
+	return color;
+}
+
 void main()
 {
 	ivec2 iCoords = ivec2(gl_GlobalInvocationID.xy);
 
 	vec4 color = process_slice(iCoords);
 
-	imageStore(img, iCoords, color);
+	imageStore(img, iCoords + ivec2(0, settings.view_offset_y), color);
 }
@@ -6,12 +6,15 @@ layout(local_size_x = 1, local_size_y = 1) in;
 layout(std430, binding = 1) buffer Settings {
 	int resolution_x;
 	int resolution_y;
+	int view_offset_y;
 } settings;
 
 layout(std430, binding = 2) buffer PlayerData {
 	vec2 position;
 	vec2 direction;
 	vec2 camera_plane;
+	float pitch;
+	float eye_height;
 } player;
 
 layout(std430, binding = 3) buffer World {
@@ -19,9 +22,17 @@ layout(std430, binding = 3) buffer World {
 	uint floor_texture_idx;
 	uint geometry_tile_width;
 	uint stride;
+	float max_wall_height;
 	uint[] geometry;
 } world;
 
+//Per column: layer count, followed by MAX_LAYERS layers of
+//line height, texture index, texture x, distance (float bits)
+//Layers are stored front to back, the last one is the wall hiding everything behind it
+#define MAX_LAYERS 6
+#define LAYER_SIZE 4
+#define SLICE_SIZE (1 + MAX_LAYERS * LAYER_SIZE)
+
 layout(std430, binding = 5) buffer SliceData {
 	int data[];
 } slice;
@@ -30,11 +41,76 @@ layout(std430, binding = 7) buffer zData {
 	double data[];
 } z_buffer;
 
+#define TILE_MASKED 1u
+
+struct Tile_Properties {
+	uint	flags;
+	float	height;
+};
+
+layout(std430, binding = 10) buffer TileData {
+	Tile_Properties list[];
+} tiles;
+
+//Pixels per world unit at distance 1, derived from the horizontal field of view
+float projection_scale()
+{
+	return float(settings.resolution_x) / (2.0 * length(player.camera_plane));
+}
+
 uint get_world_value(int x, int y)
 {
 	return world.geometry[x + y * world.stride];
 }
 
+bool is_masked(uint tile)
+{
+	return (tiles.list[tile].flags & TILE_MASKED) != 0;
+}
+
+//Opaque walls at least as high as every other wall hide whatever lies behind them
+bool is_occluder(uint tile)
+{
+	return !is_masked(tile) && tiles.list[tile].height >= world.max_wall_height;
+}
+
+bool in_bounds(int x, int y)
+{
+	return x >= 0 && y >= 0 && x < int(world.stride) && x + y * int(world.stride) < world.geometry.length();
+}
+
+double store_layer(int x, int layer, int side, int map_x, int map_y, int step_x, int step_y, double ray_dir_x, double ray_dir_y)
+{
+	double perp_wall_dist;
+
+	if (side == 0) perp_wall_dist = (map_x - player.position.x + (1 - step_x) / 2) / ray_dir_x;
+	else perp_wall_dist = (map_y - player.position.y + (1 - step_y) / 2) / ray_dir_y;
+
+	int line_height = int(projection_scale() / perp_wall_dist);
+
+	int tex_idx = int(get_world_value(map_x, map_y) - 1);
+
+	double wall_x;
+
+	if (side == 0) wall_x = player.position.y + perp_wall_dist * ray_dir_y;
+	else wall_x = player.position.x + perp_wall_dist * ray_dir_x;
+	wall_x -= floor(wall_x);
+
+	int texX = int(wall_x * double(world.geometry_tile_width));
+
+	if (side == 0 && ray_dir_x > 0) texX = int(world.geometry_tile_width) - texX - 1;
+	if (side == 1 && ray_dir_y < 0) texX = int(world.geometry_tile_width) - texX - 1;
+
+	//Store calculations
+	int base = x * SLICE_SIZE + 1 + layer * LAYER_SIZE;
+	slice.data[base + 0] = line_height;
+	slice.data[base + 1] = tex_idx;
+	slice.data[base + 2] = texX;
+	slice.data[base + 3] = floatBitsToInt(float(perp_wall_dist));
+
+	return perp_wall_dist;
+}
+
 void precompute_slice(int x)
 {
 	double camera_x = 2 * x / double(settings.resolution_x) - 1;
@@ -50,6 +126,7 @@ void precompute_slice(int x)
 	double delta_dist_x = abs(1 / ray_dir_x);
 	double delta_dist_y = abs(1 / ray_dir_y);
 	double perp_wall_dist;
+	int layer_count = 0;
 
 	int step_x;
 	int step_y;
@@ -72,8 +149,7 @@ void precompute_slice(int x)
 		side_dist_y = (map_y + 1.0 - player.position.y) * delta_dist_y;
 	}
 
-	//Maps which are not fully closed will stall the gpu, since the loop below will be infinite
-	//TODO implement protection/limit in case of a not fully closed map
+	//Rays leaving the map end without an occluder, this happens with maps enclosed by low walls
 	while (hit == 0) {
 		if (side_dist_x < side_dist_y) {
 			side_dist_x += delta_dist_x;
@@ -84,32 +160,28 @@ void precompute_slice(int x)
 			map_y += step_y;
 			side = 1;
 		}
-		if (get_world_value(map_x, map_y) > 0) hit = 1;
-	}
-
-	if (side == 0) perp_wall_dist = (map_x - player.position.x + (1 - step_x) / 2) / ray_dir_x;
-	else perp_wall_dist = (map_y - player.position.y + (1 - step_y) / 2) / ray_dir_y;
 
-	int line_height = int(settings.resolution_y / perp_wall_dist);
-
-	int tex_idx = int(get_world_value(map_x, map_y) - 1);
+		if (!in_bounds(map_x, map_y)) {
+			perp_wall_dist = 1e30;
+			break;
+		}
 
-	double wall_x;
+		uint tile = get_world_value(map_x, map_y);
+		if (tile == 0) continue;
 
-	if (side == 0) wall_x = player.position.y + perp_wall_dist * ray_dir_y;
-	else wall_x = player.position.x + perp_wall_dist * ray_dir_x;
-	wall_x -= floor(wall_x);
+		//Masked and low walls are recorded and passed through, the last layer is kept free for the occluder
+		bool occluder = is_occluder(tile);
+		if (!occluder && layer_count == MAX_LAYERS - 1) continue;
 
-	int texX = int(wall_x * double(world.geometry_tile_width));
+		perp_wall_dist = store_layer(x, layer_count, side, map_x, map_y, step_x, step_y, ray_dir_x, ray_dir_y);
+		layer_count++;
 
-	if (side == 0 && ray_dir_x > 0) texX = int(world.geometry_tile_width) - texX - 1;
-	if (side == 1 && ray_dir_y < 0) texX = int(world.geometry_tile_width) - texX - 1;
+		if (occluder) hit = 1;
+	}
 
-	//Store calculations
-	slice.data[x * 3 + 0] = line_height;
-	slice.data[x * 3 + 1] = tex_idx;
-	slice.data[x * 3 + 2] = texX;
+	slice.data[x * SLICE_SIZE] = layer_count;
 
+	//Sprites are tested against the occluder, layers in front of them are resolved per pixel by the sprite pass
 	z_buffer.data[x] = perp_wall_dist;
 }
 